@@ -64,6 +64,10 @@ pub fn main() {
         where 
             D: Deserialize<'static>,
             S: crate::valueseq::SerialSeq;
+        /// Same api, but calls give up with an error if nvim doesn't reply within timeout.
+        fn with_timeout(&self, timeout: core::time::Duration) -> impl Nvimapi;
+        /// timeout of calls not made through with_timeout. None, the default, waits forever.
+        fn set_default_timeout(&self, timeout: Option<core::time::Duration>);
     "###;
 }
 
@@ -258,10 +262,10 @@ impl From<Tabpage> for Value {
 impl TryFromValue for Buffer {
     fn try_from_value(value: Value) -> error::Result<Self> {
         let Value::Ext(id, _) = &value else {
-            return error::with_msg("expected msgpack ext.");
+            return error::with_kind(error::ErrorKind::Decode, "expected msgpack ext.");
         };
         if *id != BUFFER_ID {
-            return error::with_msg("expected id 0 for buffer");
+            return error::with_kind(error::ErrorKind::Decode, "expected id 0 for buffer");
         }
         return Ok(Self(value));
     }
@@ -269,10 +273,10 @@ impl TryFromValue for Buffer {
 impl TryFromValue for Window {
     fn try_from_value(value: Value) -> error::Result<Self> {
         let Value::Ext(id, _) = &value else {
-            return error::with_msg("expected msgpack ext.");
+            return error::with_kind(error::ErrorKind::Decode, "expected msgpack ext.");
         };
         if *id != WINDOW_ID {
-            return error::with_msg("expected id 1 for window");
+            return error::with_kind(error::ErrorKind::Decode, "expected id 1 for window");
         }
         return Ok(Self(value));
     }
@@ -280,10 +284,10 @@ impl TryFromValue for Window {
 impl TryFromValue for Tabpage {
     fn try_from_value(value: Value) -> error::Result<Self> {
         let Value::Ext(id, _) = &value else {
-            return error::with_msg("expected msgpack ext.");
+            return error::with_kind(error::ErrorKind::Decode, "expected msgpack ext.");
         };
         if *id != TABPAGE_ID {
-            return error::with_msg("expected id 2 for tabpage");
+            return error::with_kind(error::ErrorKind::Decode, "expected id 2 for tabpage");
         }
         return Ok(Self(value));
     }
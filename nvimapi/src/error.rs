@@ -5,11 +5,35 @@ use rmpv::Value;
 pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug)]
 pub struct Error {
+    pub kind: ErrorKind,
     pub msg: Cow<'static, str>,
-    pub inner: Option<Box<dyn core::error::Error>>,
+    pub inner: Option<Box<dyn core::error::Error + Send + Sync>>,
     pub inner_value: Option<Box<Value>>,
 }
 
+/// What went wrong, so that callers can handle each differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// reading or writing the connection to nvim.
+    Io,
+    /// arguments could not be encoded to msgpack.
+    Encode,
+    /// what nvim sent is not what was expected.
+    Decode,
+    /// nvim failed to do the call, like an error in vimscript or lua. msg is the message of nvim.
+    NvimException,
+    /// nvim refused arguments of the call. msg is the message of nvim.
+    NvimValidation,
+    /// connection to nvim is gone.
+    ChannelClosed,
+    /// nvim did not reply in time.
+    Timeout,
+    Other,
+}
+// type of error in [type, message] of nvim.
+const NVIM_EXCEPTION: i64 = 0;
+const NVIM_VALIDATION: i64 = 1;
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if !self.msg.is_empty() {
@@ -34,12 +58,12 @@ pub fn with_msg<T>(msg: impl Into<Cow<'static, str>>) -> Result<T> {
         Error::from_msg(msg)
     )
 }
-pub fn with_inner<T>(inner: impl core::error::Error + 'static) -> Result<T> {
+pub fn with_inner<T>(inner: impl core::error::Error + Send + Sync + 'static) -> Result<T> {
     Err(
         Error::from_inner(inner)
     )
 }
-pub fn with_msg_inner<T>(msg: impl Into<Cow<'static, str>>, inner: impl core::error::Error + 'static) -> Result<T> {
+pub fn with_msg_inner<T>(msg: impl Into<Cow<'static, str>>, inner: impl core::error::Error + Send + Sync + 'static) -> Result<T> {
     Err(
         Error::new(msg, Some(inner), None)
     )
@@ -54,66 +78,125 @@ pub fn with_msg_value<T>(msg: impl Into<Cow<'static, str>>, value: Value) -> Res
         Error::from_msg_value(msg, value)
     )
 }
+pub fn with_kind<T>(kind: ErrorKind, msg: impl Into<Cow<'static, str>>) -> Result<T> {
+    Err(
+        Error::from_msg(msg).of_kind(kind)
+    )
+}
 impl Error {
-    pub fn new(msg: impl Into<Cow<'static, str>>, inner: Option<impl core::error::Error + 'static>, inner_value: Option<Value>) -> Self {
-        let inner: Option<Box<dyn core::error::Error + 'static>> = 
+    pub fn new(msg: impl Into<Cow<'static, str>>, inner: Option<impl core::error::Error + Send + Sync + 'static>, inner_value: Option<Value>) -> Self {
+        let inner: Option<Box<dyn core::error::Error + Send + Sync + 'static>> = 
             if let Some(inner) = inner {
                 Some(Box::new(inner))
             } else {None};
-        Self { msg: msg.into(), inner, inner_value: inner_value.map(Box::new) }
+        Self { kind: ErrorKind::Other, msg: msg.into(), inner, inner_value: inner_value.map(Box::new) }
     }
 
-    pub fn from_inner(inner: impl core::error::Error + 'static) -> Self {
-        Error { msg: "".into(), inner: Some(Box::new(inner)), inner_value: None }
+    pub fn from_inner(inner: impl core::error::Error + Send + Sync + 'static) -> Self {
+        Error { kind: ErrorKind::Other, msg: "".into(), inner: Some(Box::new(inner)), inner_value: None }
     }
     pub fn from_msg(msg: impl Into<Cow<'static, str>>) -> Self {
-        Error { msg: msg.into(), inner: None, inner_value: None }
+        Error { kind: ErrorKind::Other, msg: msg.into(), inner: None, inner_value: None }
     }
     pub fn from_value(value: Value) -> Self {
-        Error { msg: "".into(), inner: None, inner_value: Some(Box::new(value)) }
+        Error { kind: ErrorKind::Other, msg: "".into(), inner: None, inner_value: Some(Box::new(value)) }
     }
     pub fn from_msg_value(msg: impl Into<Cow<'static, str>>, value: Value) -> Self {
-        Error { msg: msg.into(), inner: None, inner_value: Some(Box::new(value)) }
+        Error { kind: ErrorKind::Other, msg: msg.into(), inner: None, inner_value: Some(Box::new(value)) }
+    }
+    /// connection to nvim is gone. Writing to it fails with Io, waiting for reply with ChannelClosed.
+    pub fn disconnected(&self) -> bool {
+        matches!(self.kind, ErrorKind::Io | ErrorKind::ChannelClosed)
+    }
+    pub fn of_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    /// from error of a response, which nvim sends as [type, message].
+    pub fn from_nvim(value: Value) -> Self {
+        let (kind, msg) = match value.as_array().map(Vec::as_slice) {
+            Some([type_, msg]) => {
+                let kind = match type_.as_i64() {
+                    Some(NVIM_EXCEPTION) => ErrorKind::NvimException,
+                    Some(NVIM_VALIDATION) => ErrorKind::NvimValidation,
+                    _ => ErrorKind::Other,
+                };
+                (kind, msg.as_str().unwrap_or_default().to_owned())
+            },
+            _ => (ErrorKind::Other, String::new()),
+        };
+        Error::from_msg_value(msg, value).of_kind(kind)
     }
 }
 
-impl<T: 'static> From<tokio::sync::mpsc::error::SendError<T>> for Error {
-    fn from(value: tokio::sync::mpsc::error::SendError<T>) -> Self {
-        Self::from_inner(value)
+// what couldn't be sent is not kept, it may not be Send.
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for Error {
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        Self::from_msg("channel closed").of_kind(ErrorKind::ChannelClosed)
     }
 }
 impl From<tokio::sync::oneshot::error::RecvError> for Error {
     fn from(value: tokio::sync::oneshot::error::RecvError) -> Self {
-        Self::from_inner(value)
+        Self::from_inner(value).of_kind(ErrorKind::ChannelClosed)
+    }
+}
+impl From<tokio::time::error::Elapsed> for Error {
+    fn from(value: tokio::time::error::Elapsed) -> Self {
+        Self::from_inner(value).of_kind(ErrorKind::Timeout)
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::from_inner(value).of_kind(ErrorKind::Io)
     }
 }
 impl From<rmp_serde::encode::Error> for Error {
     fn from(value: rmp_serde::encode::Error) -> Self {
-        Self::from_inner(value)
+        let kind = match value {
+            rmp_serde::encode::Error::InvalidValueWrite(_) => ErrorKind::Io,
+            _ => ErrorKind::Encode,
+        };
+        Self::from_inner(value).of_kind(kind)
     }
 }
 impl From<rmpv::ext::Error> for Error {
     fn from(value: rmpv::ext::Error) -> Self {
-        Self::from_inner(value)
+        Self::from_inner(value).of_kind(ErrorKind::Decode)
     }
 }
 
 impl serde::de::Error for Error {
     fn custom<T>(msg:T) -> Self where T:Display {
         Error {
+            kind: ErrorKind::Decode,
             msg: msg.to_string().into(),
             inner: None,
             inner_value: None,
         }
     }
 }
+// it only fails to write.
 impl From<rmpv::encode::Error> for Error {
     fn from(value: rmpv::encode::Error) -> Self {
-        Error::from_inner(value)
+        Error::from_inner(value).of_kind(ErrorKind::Io)
     }
 }
 impl From<Value> for Error {
     fn from(value: Value) -> Self {
-        Error::from_value(value)
+        Error::from_nvim(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rmpv::Value;
+    use super::{Error, ErrorKind};
+    #[test]
+    fn nvim_error() {
+        let e = Error::from(Value::Array(vec![Value::from(1), Value::from("Invalid buffer id: 9")]));
+        assert_eq!(e.kind, ErrorKind::NvimValidation);
+        assert_eq!(e.msg, "Invalid buffer id: 9");
+        let e = Error::from(Value::Array(vec![Value::from(0), Value::from("Vim:E492")]));
+        assert_eq!(e.kind, ErrorKind::NvimException);
     }
 }
@@ -1,12 +1,20 @@
 use core::ops::Deref;
 use std::rc::Rc;
 
-use crate::{msgrpc::Request, nvimapi::{Nvimapi, notification::Notification}};
+use crate::{error::Error, msgrpc::Request, nvimapi::{Nvimapi, notification::Notification}};
 #[allow(async_fn_in_trait)]
 pub trait Handler {
     async fn notify(&self, nvim: &impl Nvimapi, notification: Notification);
     async fn request(&self, nvim: &impl Nvimapi, request: Box<Request>);
     async fn init(&self, nvim: &impl Nvimapi);
+    /// something nvim sent could not be understood, and was skipped.
+    async fn error(&self, _nvim: &impl Nvimapi, error: Box<Error>) {
+        log::warn!("{error}");
+    }
+    /// connection to nvim is gone, calls to nvim won't work anymore. Last thing handler is told.
+    async fn disconnected(&self, reason: Box<Error>) {
+        log::debug!("disconnected: {reason}");
+    }
 }
 
 impl<H: Handler> Handler for Rc<H> {
@@ -19,11 +27,20 @@ impl<H: Handler> Handler for Rc<H> {
     async fn init(&self, nvim: &impl Nvimapi) {
         self.deref().init(nvim).await
     }
+    async fn error(&self, nvim: &impl Nvimapi, error: Box<Error>) {
+        self.deref().error(nvim, error).await
+    }
+    async fn disconnected(&self, reason: Box<Error>) {
+        self.deref().disconnected(reason).await
+    }
 }
 
 pub enum MsgForHandler {
     Request(Box<Request>),
     Notification(Notification),
+    Error(Box<Error>),
+    // reader is done, nothing comes after it.
+    Disconnected(Box<Error>),
 }
 
 #[cfg(test)]
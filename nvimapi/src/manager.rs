@@ -1,7 +1,7 @@
 use std::{io::{Read, Write}, rc::Rc};
 use log::debug;
-use tokio::{runtime::LocalRuntime, sync::mpsc};
-use crate::{MsgToReader, Nvimapi, Nvimrpc, handler::{Handler, MsgForHandler}, msgrpc::Request, nvimapi::notification::Notification, readloop};
+use tokio::{runtime::LocalRuntime, sync::mpsc, task::JoinHandle};
+use crate::{MsgToReader, Nvimapi, error::Error, Nvimrpc, handler::{Handler, MsgForHandler}, msgrpc::Request, nvimapi::notification::Notification, readloop};
 use core::ops::Deref;
 
 async fn loopy<H, W>(
@@ -17,13 +17,26 @@ where
     use MsgForHandler as Mfh;
     let handler = Rc::new(handler);
     rt.spawn_local(init_handler(nvim.clone(), handler.clone()));
+    // notifications still being handled. disconnected waits for them, as they came before it.
+    let mut notifications: Vec<JoinHandle<()>> = Vec::new();
     while let Some(msg) = rx.recv().await {
         match msg {
             Mfh::Request(request) => {
                 rt.spawn_local(send_request_to_handler(nvim.clone(), handler.clone(), request));
             },
             Mfh::Notification(notification) => {
-                rt.spawn_local(send_notification_to_handler(nvim.clone(), handler.clone(), notification));
+                notifications.retain(|task| !task.is_finished());
+                notifications.push(rt.spawn_local(send_notification_to_handler(nvim.clone(), handler.clone(), notification)));
+            },
+            Mfh::Error(error) => {
+                rt.spawn_local(send_error_to_handler(nvim.clone(), handler.clone(), error));
+            },
+            // not spawned, handler is done with it before start finishes.
+            Mfh::Disconnected(reason) => {
+                for task in notifications.drain(..) {
+                    let _ = task.await;
+                }
+                handler.disconnected(reason).await;
             },
         }
     }
@@ -48,6 +61,9 @@ async fn send_request_to_handler<W: Write>(nvim: Rc<Nvimrpc<W>>,handler: Rc<impl
 async fn send_notification_to_handler<W: Write>(nvim: Rc<Nvimrpc<W>>, handler: Rc<impl Handler>, notification: Notification) {
     handler.notify(nvim.deref(), notification).await
 }
+async fn send_error_to_handler<W: Write>(nvim: Rc<Nvimrpc<W>>, handler: Rc<impl Handler>, error: Box<Error>) {
+    handler.error(nvim.deref(), error).await
+}
 async fn init_handler<W:Write>(nvim: Rc<Nvimrpc<W>>, handler: Rc<impl Handler>,) {
     handler.init(nvim.deref()).await
 }
@@ -63,13 +79,16 @@ where
 {
     let (tx_to_handler, rx_for_handler) = mpsc::channel::<MsgForHandler>(10);
     let (tx_to_reader, rx_for_reader) = mpsc::channel::<MsgToReader>(10);
+    let (tx_cancel, rx_cancel) = mpsc::unbounded_channel::<u32>();
     std::thread::spawn(|| {
-        readloop::readloop(reader, rx_for_reader, tx_to_handler);
+        readloop::readloop(reader, rx_for_reader, rx_cancel, tx_to_handler);
     });
     let nvim = Nvimrpc {
         tx_to_reader,
+        tx_cancel,
         msgid: Default::default(),
         write: core::cell::RefCell::new(writer),
+        default_timeout: Default::default(),
     };
     let nvim = Rc::new(nvim);
     return (loopy(rx_for_handler, nvim.clone(), handler, rt), nvim.clone(),);
@@ -11,6 +11,8 @@ pub enum Message {
     Request(Request),
     Response(Response),
     Notification(Notification),
+    // type code which msgrpc doesn't have. Rest of it is skipped.
+    Unknown(u8),
 }
 
 impl<'de> Deserialize<'de> for Message {
@@ -51,7 +53,10 @@ impl<'de> Deserialize<'de> for Message {
                         let inner = Request::deserialize(ContSeq::new(seq))?;
                         return Ok(Message::Request(inner));
                     },
-                    c => {unreachable!("msgrpc doesn't have type {c}")}
+                    c => {
+                        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+                        return Ok(Message::Unknown(c));
+                    }
                 }
             }
 
@@ -1,11 +1,11 @@
 use crate::{MsgToReader, msgrpc::{self, RESPONSE_CODE}, valueseq};
 pub use crate::generated::{Nvimapi, NvimapiNr};
-use core::{cell::{Cell, RefCell}, ops::{Deref, DerefMut}};
+use core::{cell::{Cell, RefCell}, ops::{Deref, DerefMut}, time::Duration};
 use std::{io::Write, rc::Rc};
 use rmpv::Value;
 use serde::Deserialize;
 use tokio::sync::{mpsc, oneshot};
-use crate::{error, nvimapi::valueseq::{SerialSeq, ValueSeq}};
+use crate::{error::{self, ErrorKind}, nvimapi::valueseq::{SerialSeq, ValueSeq}};
 pub use crate::generated::UiEvent;
 pub mod notification;
 pub const BUFFER_ID:  i8 = 0;
@@ -46,6 +46,14 @@ impl<T: Nvimapi> Nvimapi for Rc<T> {
         S: crate::valueseq::SerialSeq {
             self.deref().call_fn(fn_name, args).await
     }
+
+    fn with_timeout(&self, timeout: Duration) -> impl Nvimapi {
+        self.deref().with_timeout(timeout)
+    }
+
+    fn set_default_timeout(&self, timeout: Option<Duration>) {
+        self.deref().set_default_timeout(timeout)
+    }
 }
 
 // will keep a writer to encode with.
@@ -53,8 +61,31 @@ impl<T: Nvimapi> Nvimapi for Rc<T> {
 pub struct Nvimrpc<W: Write>
 {
     pub(crate) tx_to_reader: mpsc::Sender<MsgToReader>,
+    // msgids of calls not waiting anymore. Unbounded, so that a cancel is never lost.
+    pub(crate) tx_cancel: mpsc::UnboundedSender<u32>,
     pub(crate) msgid: Cell<u32>,
     pub(crate) write: RefCell<W>,
+    // of calls not made through with_timeout.
+    pub(crate) default_timeout: Cell<Option<Duration>>,
+}
+/// Nvimapi of with_timeout, its calls give up after timeout.
+pub struct Timed<'a, W: Write> {
+    rpc: &'a Nvimrpc<W>,
+    timeout: Duration,
+}
+// tells readloop to forget the call, if it's dropped before the response came. Like when it timed
+// out, or the future of the call was dropped.
+struct PendingGuard<'a> {
+    tx_cancel: &'a mpsc::UnboundedSender<u32>,
+    msg_id: u32,
+    answered: bool,
+}
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if self.answered { return }
+        // fails only when reader is gone, then nothing is pending anyway.
+        let _ = self.tx_cancel.send(self.msg_id);
+    }
 }
 // fn try_send(tx: &std::sync::mpsc::SyncSender<MsgToReader>, msg: MsgToReader) -> error::Result<()> {
 //     if let Err(e) = tx.try_send(msg) {
@@ -93,17 +124,7 @@ impl<W: Write> Nvimapi for Nvimrpc<W>
     where 
         R: TryFromValue
     {
-        let msg_id = self.get_next_msg_id();
-        let request = msgrpc::create_request_value(msg_id, fn_name, args);
-        let (sender, rx) = oneshot::channel::<Result<Value,Value>>();
-        let msg = MsgToReader::new(msg_id, sender);
-        // this is sent to readloop first, to avoid the possibility that readloop receives the
-        // reply from nvim, but does not have received the corres_request yet.
-        self.tx_to_reader.send(msg).await.unwrap();
-        // socket buffer is around 200 kb in linux, so it shouldn't block due to full.
-        rmpv::encode::write_value(self.write.borrow_mut().deref_mut(), &request)?;
-        let rv = rx.await??;
-        return R::try_from_value(rv);
+        self.call_fn_wv_timed(fn_name, args, self.default_timeout.get()).await
     }
 
     async fn call_fn<D,S>(&self, fn_name: &str, args: S) -> error::Result<D>
@@ -111,20 +132,53 @@ impl<W: Write> Nvimapi for Nvimrpc<W>
         D: Deserialize<'static>,
         S: SerialSeq,
     {
-        let msg_id = self.get_next_msg_id();
-        let request = msgrpc::create_request_ser(msg_id, fn_name, args);
-        let (sender, rx) = oneshot::channel::<Result<Value, Value>>();
-        let msg = MsgToReader::new(msg_id, sender);
-        // this is sent to readloop first, to avoid the possibility that readloop receives the
-        // reply from nvim, but does not have received the corres_request yet.
-        self.tx_to_reader.send(msg).await.unwrap();
-        // socket buffer is around 200 kb in linux, so it shouldn't block due to full.
-        rmp_serde::encode::write_named(self.write.borrow_mut().deref_mut(), &request)?;
-        let rv = rx.await??;
-        return Ok(D::deserialize(rv)?);
+        self.call_fn_timed(fn_name, args, self.default_timeout.get()).await
     }
 
     fn nr(&self) -> &impl NvimapiNr { self }
+
+    fn with_timeout(&self, timeout: Duration) -> impl Nvimapi {
+        Timed { rpc: self, timeout }
+    }
+
+    fn set_default_timeout(&self, timeout: Option<Duration>) {
+        self.default_timeout.set(timeout);
+    }
+}
+
+impl<W: Write> Nvimapi for Timed<'_, W> {
+    fn nr(&self) -> &impl NvimapiNr { self.rpc }
+
+    fn send_response_wv(&self, msgid: i32, error: Value, result: Value) -> error::Result<()> {
+        self.rpc.send_response_wv(msgid, error, result)
+    }
+
+    fn send_response(&self, msgid: i32, error: impl serde::Serialize, result: impl serde::Serialize) -> error::Result<()> {
+        self.rpc.send_response(msgid, error, result)
+    }
+
+    async fn call_fn_wv<R>(&self, fn_name: String, args: impl ValueSeq) -> error::Result<R>
+    where 
+        R: TryFromValue
+    {
+        self.rpc.call_fn_wv_timed(fn_name, args, Some(self.timeout)).await
+    }
+
+    async fn call_fn<D,S>(&self, fn_name: &str, args: S) -> error::Result<D>
+    where 
+        D: Deserialize<'static>,
+        S: SerialSeq,
+    {
+        self.rpc.call_fn_timed(fn_name, args, Some(self.timeout)).await
+    }
+
+    fn with_timeout(&self, timeout: Duration) -> impl Nvimapi {
+        Timed { rpc: self.rpc, timeout }
+    }
+
+    fn set_default_timeout(&self, timeout: Option<Duration>) {
+        self.rpc.set_default_timeout(timeout);
+    }
 }
 
 impl<W: Write> NvimapiNr for Nvimrpc<W> {
@@ -160,6 +214,58 @@ impl<W: Write> Nvimrpc<W> {
         self.msgid.update(|m| m+1);
         return msg_id;
     }
+    async fn call_fn_wv_timed<R>(&self, fn_name: String, args: impl ValueSeq, timeout: Option<Duration>) -> error::Result<R>
+    where 
+        R: TryFromValue
+    {
+        let msg_id = self.get_next_msg_id();
+        let request = msgrpc::create_request_value(msg_id, fn_name.clone(), args);
+        let (rx, mut guard) = self.expect_response(msg_id).await?;
+        // socket buffer is around 200 kb in linux, so it shouldn't block due to full.
+        rmpv::encode::write_value(self.write.borrow_mut().deref_mut(), &request)?;
+        let rv = wait_response(rx, &mut guard, &fn_name, timeout).await?;
+        return R::try_from_value(rv);
+    }
+    async fn call_fn_timed<D,S>(&self, fn_name: &str, args: S, timeout: Option<Duration>) -> error::Result<D>
+    where 
+        D: Deserialize<'static>,
+        S: SerialSeq,
+    {
+        let msg_id = self.get_next_msg_id();
+        let request = msgrpc::create_request_ser(msg_id, fn_name, args);
+        let (rx, mut guard) = self.expect_response(msg_id).await?;
+        // socket buffer is around 200 kb in linux, so it shouldn't block due to full.
+        rmp_serde::encode::write_named(self.write.borrow_mut().deref_mut(), &request)?;
+        let rv = wait_response(rx, &mut guard, fn_name, timeout).await?;
+        return Ok(D::deserialize(rv)?);
+    }
+    // tell readloop about the call, before it's sent to nvim. So that readloop doesn't receive the
+    // reply from nvim without knowing who it's for.
+    async fn expect_response(&self, msg_id: u32) -> error::Result<(oneshot::Receiver<Result<Value, Value>>, PendingGuard<'_>)> {
+        let (sender, rx) = oneshot::channel::<Result<Value, Value>>();
+        self.tx_to_reader.send(MsgToReader::new(msg_id, sender)).await?;
+        let guard = PendingGuard { tx_cancel: &self.tx_cancel, msg_id, answered: false };
+        return Ok((rx, guard));
+    }
+}
+async fn wait_response(
+    rx: oneshot::Receiver<Result<Value, Value>>,
+    guard: &mut PendingGuard<'_>,
+    fn_name: &str,
+    timeout: Option<Duration>,
+) -> error::Result<Value> {
+    let rv = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, rx).await {
+            Ok(rv) => rv,
+            Err(elapsed) => {
+                let msg = format!("nvim did not reply to {fn_name} in {timeout:?}");
+                return Err(error::Error::new(msg, Some(elapsed), None).of_kind(ErrorKind::Timeout));
+            },
+        },
+        None => rx.await,
+    };
+    guard.answered = true;
+    return Ok(rv??);
 }
 pub trait TryFromValue {
     fn try_from_value(value: Value) -> error::Result<Self> where Self: Sized;
@@ -170,7 +276,7 @@ macro_rules! impl_from_value {
         impl TryFromValue for $type {
             fn try_from_value(value: Value) -> error::Result<Self> {
                 let Ok(rv) = Self::try_from(value) else {
-                    return error::with_msg(format!("Failed to convert Value instance to {}.", stringify!($type)));
+                    return error::with_kind(ErrorKind::Decode, format!("Failed to convert Value instance to {}.", stringify!($type)));
                 };
                 return Ok(rv);
             }
@@ -190,7 +296,7 @@ impl TryFromValue for Value {
 // when specialization arrives
 impl<V: TryFromValue> TryFromValue for Vec<V> {
     fn try_from_value(value: Value) -> error::Result<Self> where Self: Sized {
-        let Value::Array(array) = value else {return error::with_msg("expected array.")};
+        let Value::Array(array) = value else {return error::with_kind(ErrorKind::Decode, "expected array.")};
         let mut rv = Vec::with_capacity(array.len());
         for value in array {
             let v = V::try_from_value(value)?;
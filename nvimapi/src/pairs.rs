@@ -105,7 +105,7 @@ where
         Self: Sized,
     {
         let Value::Map(value) = value else {
-            return error::with_msg("expecting map");
+            return error::with_kind(error::ErrorKind::Decode, "expecting map");
         };
         let mut rv = Vec::with_capacity(value.len());
         for (k, v) in value {
@@ -1,97 +1,300 @@
 use core::ops::ControlFlow;
-use std::{collections::VecDeque, io::Read};
+use std::{collections::{HashMap, VecDeque}, io::Read, time::{Duration, Instant}};
 use log::{debug, warn};
-use tokio::sync::mpsc;
-use crate::{MsgToReader, PendingRequest, handler::MsgForHandler, msgrpc::Message};
+use rmpv::Value;
+use tokio::sync::{mpsc, oneshot};
+use crate::{MsgToReader, error::{Error, ErrorKind}, handler::MsgForHandler, msgrpc::Message};
+
+// waiting longer than this for handler to take a notification is worth a warning.
+const SLOW_HANDLER: Duration = Duration::from_millis(500);
+// msgids of calls given up on, whose response is not an error when it comes. Only recent ones
+// are kept, nvim may never answer some.
+const MAX_CANCELLED: usize = 64;
+
+// calls waiting for response, by msgid. nvim doesn't always respond in order, like when a request
+// is blocked in rpcrequest.
+#[derive(Default)]
+struct PendingRequests {
+    senders: HashMap<u32, oneshot::Sender<Result<Value, Value>>>,
+    cancelled: VecDeque<u32>,
+}
+impl PendingRequests {
+    fn cancel(&mut self, msg_id: u32) {
+        if self.senders.remove(&msg_id).is_none() { return }
+        if self.cancelled.len() == MAX_CANCELLED {
+            self.cancelled.pop_front();
+        }
+        self.cancelled.push_back(msg_id);
+    }
+    /// Sender for response of msg_id. Err(true) if it was cancelled, Err(false) if it's not known.
+    fn take(&mut self, msg_id: u32) -> Result<oneshot::Sender<Result<Value, Value>>, bool> {
+        if let Some(sender) = self.senders.remove(&msg_id) { return Ok(sender) }
+        let Some(i) = self.cancelled.iter().position(|&id| id == msg_id) else { return Err(false) };
+        self.cancelled.remove(i);
+        return Err(true);
+    }
+}
 
 
 pub fn readloop<R: Read>(
     mut reader: R,
     mut rx: mpsc::Receiver<MsgToReader>,
+    mut rx_cancel: mpsc::UnboundedReceiver<u32>,
     tx: mpsc::Sender<MsgForHandler>,
 ) {
-    let mut unprocessed_request = Option::<PendingRequest>::None;
+    let mut pending_requests = PendingRequests::default();
+    // how far handler has fallen behind, for logs.
+    let mut max_depth = 0;
+    let mut overflows = 0;
     'outer: loop {
-        let message: Message = 
-            match rmp_serde::decode::from_read(&mut reader) {
-                Ok(message) => message,
-                Err(e) => {
-                    debug!("{e}");
+        // a frame is read whole first. One of unexpected shape is then skipped, the next one
+        // starts right after it.
+        let value = match rmpv::decode::read_value(&mut reader) {
+            Ok(value) => value,
+            Err(e) => {
+                // read failed, connection is gone. Or nesting was too deep, then where the next
+                // message starts is not known either.
+                let kind = match e {
+                    rmpv::decode::Error::InvalidMarkerRead(_)
+                    | rmpv::decode::Error::InvalidDataRead(_) => ErrorKind::Io,
+                    rmpv::decode::Error::DepthLimitExceeded => ErrorKind::Decode,
+                };
+                let reason = Error::from_inner(e).of_kind(kind);
+                debug!("{reason}");
+                let _ = tx.blocking_send(MsgForHandler::Disconnected(Box::new(reason)));
+                break 'outer;
+            },
+        };
+        let message: Message = match rmpv::ext::from_value(value) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = Error::from(e);
+                warn!("message skipped: {error}");
+                if tx.blocking_send(MsgForHandler::Error(Box::new(error))).is_err() {
+                    warn!("channel gone");
                     break 'outer;
-                },
-            };
+                }
+                continue;
+            },
+        };
         match message {
+            Message::Unknown(type_) => {
+                let error = Error::from_msg(format!("msgrpc doesn't have type {type_}, message skipped"));
+                let msg = MsgForHandler::Error(Box::new(error.of_kind(ErrorKind::Decode)));
+                if tx.blocking_send(msg).is_err() {
+                    warn!("channel gone");
+                    break 'outer;
+                }
+            },
             Message::Request(request) => {
                 let msg = MsgForHandler::Request(Box::new(request));
-                tx.blocking_send(msg).unwrap();
+                if tx.blocking_send(msg).is_err() {
+                    warn!("channel gone");
+                    break 'outer;
+                }
             },
             Message::Response(response) => {
+                // info about a call is sent to me before the call is sent to nvim. So it's in
+                // the channel by now, if it's not in the map already.
+                if take_pending_requests(&mut rx, &mut rx_cancel, &mut pending_requests).is_break() {
+                    debug!("channel gone");
+                    break 'outer;
+                }
                 let msgid = response.msgid;
-                let corres_request = 
-                    if let Some(unprocessed_request) = unprocessed_request.take() {
-                        unprocessed_request
-                    } else {
-                        match rx.try_recv() {
-                            Ok(msg) => msg.pending_request(),
-                            Err(e) => {
-                                // empty means the info about this call was not sent to me.
-                                // As info is sent to me before sending the call to nvim. It's not
-                                // possible miss the info.
-                                if mpsc::error::TryRecvError::Empty == e { continue; }
-                                else {
-                                    debug!("channel gone");
-                                    break 'outer;
-                                }
-                            },
+                let sender = match pending_requests.take(msgid) {
+                    Ok(sender) => sender,
+                    Err(true) => {
+                        debug!("response for cancelled msgid: {msgid}");
+                        continue;
+                    },
+                    Err(false) => {
+                        let error = Error::from_msg_value(format!("response for msgid: {msgid}, which was never requested"), response.result.unwrap_or_else(|e| e))
+                            .of_kind(ErrorKind::Decode);
+                        log::error!("{error}");
+                        if tx.blocking_send(MsgForHandler::Error(Box::new(error))).is_err() {
+                            warn!("channel gone");
+                            break 'outer;
                         }
-                    };
-                if msgid != corres_request.msg_id {
-                    log::trace!("response for msgid: {msgid}, with no receiver");
-                    unprocessed_request = Some(corres_request);
-                }
-                else if corres_request.sender.send(response.result).is_err() {
-                    warn!("return value channel dropped for msg id: {}", corres_request.msg_id);
+                        continue;
+                    },
+                };
+                if sender.send(response.result).is_err() {
+                    warn!("return value channel dropped for msg id: {msgid}");
                 }
             },
             Message::Notification(notify) => {
-                if let Err(e) = tx.try_send(MsgForHandler::Notification(notify)) {
-                    match e {
-                        mpsc::error::TrySendError::Full(e) => {
-                            let MsgForHandler::Notification(notify) = e else {unreachable!()};
-                            warn!("channel to ui full, dropped notification {}", notify.name());
-                        },
-                        mpsc::error::TrySendError::Closed(_) => {
-                            warn!("channel gone");
-                            break 'outer;
-                        },
-                    }
+                // a dropped redraw leaves screen wrong for good. So when handler is behind,
+                // reading waits for it, and nvim waits on the socket.
+                let msg = match tx.try_send(MsgForHandler::Notification(notify)) {
+                    Ok(()) => {
+                        let depth = tx.max_capacity() - tx.capacity();
+                        if depth > max_depth {
+                            max_depth = depth;
+                            debug!("most notifications queued for handler so far: {depth}");
+                        }
+                        continue;
+                    },
+                    Err(mpsc::error::TrySendError::Full(msg)) => msg,
+                    Err(mpsc::error::TrySendError::Closed(_)) => {
+                        warn!("channel gone");
+                        break 'outer;
+                    },
+                };
+                overflows += 1;
+                debug!("channel to ui full with {} messages, waiting for it. Full {overflows} times so far", tx.max_capacity());
+                let waiting_since = Instant::now();
+                if tx.blocking_send(msg).is_err() {
+                    warn!("channel gone");
+                    break 'outer;
+                }
+                let waited = waiting_since.elapsed();
+                if waited > SLOW_HANDLER {
+                    warn!("channel to ui stayed full for {waited:?}, handler is falling behind");
                 }
             },
         }
     }
 }
 
-fn _check_messages_from_handler(rx: &std::sync::mpsc::Receiver<MsgToReader>, pending_requests: &mut VecDeque<PendingRequest>) -> ControlFlow<()> {
-    loop { // check for internal messages. Probably from handler.
+// move whatever handler sent to me into pending_requests. Cancels come after, a call is sent to me
+// before it can be cancelled.
+fn take_pending_requests(
+    rx: &mut mpsc::Receiver<MsgToReader>,
+    rx_cancel: &mut mpsc::UnboundedReceiver<u32>,
+    pending_requests: &mut PendingRequests,
+) -> ControlFlow<()> {
+    loop {
         match rx.try_recv() {
             Ok(msgfh) => {
                 match msgfh {
                     MsgToReader::PendingRequest(pending_request) => {
-                        pending_requests.push_back(pending_request);
+                        pending_requests.senders.insert(pending_request.msg_id, pending_request.sender);
                     },
                     MsgToReader::Other => {unimplemented!()},
                 }
             },
             Err(e) => {
                 match e {
-                    std::sync::mpsc::TryRecvError::Empty => break,
-                    std::sync::mpsc::TryRecvError::Disconnected => {
-                        warn!("channel to handler gone.");
-                        return ControlFlow::Break(());
-                    },
+                    mpsc::error::TryRecvError::Empty => break,
+                    mpsc::error::TryRecvError::Disconnected => return ControlFlow::Break(()),
                 }
             },
         }
     }
+    while let Ok(msg_id) = rx_cancel.try_recv() {
+        pending_requests.cancel(msg_id);
+    }
     return ControlFlow::Continue(());
 }
+
+#[cfg(test)]
+mod tests {
+    use rmpv::Value;
+    use tokio::sync::{mpsc, oneshot};
+    use crate::{MsgToReader, error::ErrorKind, handler::MsgForHandler, msgrpc::{NOTIFICATION_CODE, RESPONSE_CODE}};
+    use core::{cell::RefCell, task::{Context, Waker}};
+    use crate::{Nvimapi, Nvimrpc};
+    use super::{PendingRequests, readloop, take_pending_requests};
+
+    fn response(msgid: u32, result: &str) -> Vec<u8> {
+        rmp_serde::to_vec(&(RESPONSE_CODE, msgid, (), result)).unwrap()
+    }
+    #[test]
+    fn responses_out_of_order() {
+        let (tx_to_reader, rx) = mpsc::channel(10);
+        let (_tx_cancel, rx_cancel) = mpsc::unbounded_channel();
+        let (tx, mut rx_for_handler) = mpsc::channel(10);
+        let (sender1, mut rx1) = oneshot::channel();
+        let (sender2, mut rx2) = oneshot::channel();
+        tx_to_reader.try_send(MsgToReader::new(1, sender1)).unwrap();
+        tx_to_reader.try_send(MsgToReader::new(2, sender2)).unwrap();
+        let mut input = response(2, "two");
+        // nobody asked for 3, handler is told.
+        input.extend(response(3, "three"));
+        input.extend(response(1, "one"));
+        readloop(input.as_slice(), rx, rx_cancel, tx);
+        assert_eq!(rx1.try_recv().unwrap(), Ok(Value::from("one")));
+        assert_eq!(rx2.try_recv().unwrap(), Ok(Value::from("two")));
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Error(e)) if e.kind == ErrorKind::Decode));
+    }
+    #[test]
+    fn notifications_wait_for_full_channel() {
+        let (_tx_to_reader, rx) = mpsc::channel(10);
+        let (_tx_cancel, rx_cancel) = mpsc::unbounded_channel();
+        let (tx, mut rx_for_handler) = mpsc::channel(1);
+        let mut input = Vec::new();
+        for name in ["one", "two", "three"] {
+            input.extend(rmp_serde::to_vec(&(NOTIFICATION_CODE, name, [(); 0])).unwrap());
+        }
+        let reader = std::thread::spawn(move || readloop(input.as_slice(), rx, rx_cancel, tx));
+        let mut names = Vec::new();
+        while let Some(MsgForHandler::Notification(notify)) = rx_for_handler.blocking_recv() {
+            names.push(notify.name().to_owned());
+        }
+        reader.join().unwrap();
+        assert_eq!(names, ["one", "two", "three"]);
+    }
+    #[test]
+    fn unknown_message_type() {
+        let (_tx_to_reader, rx) = mpsc::channel(10);
+        let (_tx_cancel, rx_cancel) = mpsc::unbounded_channel();
+        let (tx, mut rx_for_handler) = mpsc::channel(10);
+        let mut input = rmp_serde::to_vec(&(9, "what", [1, 2])).unwrap();
+        input.extend(rmp_serde::to_vec(&(NOTIFICATION_CODE, "after", [(); 0])).unwrap());
+        readloop(input.as_slice(), rx, rx_cancel, tx);
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Error(e)) if e.kind == ErrorKind::Decode));
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Notification(n)) if n.name() == "after"));
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Disconnected(e)) if e.kind == ErrorKind::Io));
+    }
+    #[test]
+    fn malformed_message_skipped() {
+        let (_tx_to_reader, rx) = mpsc::channel(10);
+        let (_tx_cancel, rx_cancel) = mpsc::unbounded_channel();
+        let (tx, mut rx_for_handler) = mpsc::channel(10);
+        // notification with a map for its name.
+        let mut input = rmp_serde::to_vec(&(NOTIFICATION_CODE, [(1, 2)].into_iter().collect::<std::collections::HashMap<_, _>>(), [(); 0])).unwrap();
+        input.extend(rmp_serde::to_vec(&(NOTIFICATION_CODE, "after", [(); 0])).unwrap());
+        readloop(input.as_slice(), rx, rx_cancel, tx);
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Error(e)) if e.kind == ErrorKind::Decode));
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Notification(n)) if n.name() == "after"));
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Disconnected(e)) if e.kind == ErrorKind::Io));
+    }
+    #[test]
+    fn dropped_call_cancelled_with_full_queue() {
+        let (tx_to_reader, mut rx) = mpsc::channel(1);
+        let (tx_cancel, mut rx_cancel) = mpsc::unbounded_channel();
+        let rpc = Nvimrpc {
+            tx_to_reader, tx_cancel,
+            msgid: Default::default(),
+            write: RefCell::new(Vec::new()),
+            default_timeout: Default::default(),
+        };
+        let mut call = Box::pin(rpc.call_fn::<Value, _>("nvim_get_mode", [(); 0]));
+        assert!(call.as_mut().poll(&mut Context::from_waker(Waker::noop())).is_pending());
+        // queue to reader is full with the call itself.
+        assert_eq!(rpc.tx_to_reader.capacity(), 0);
+        drop(call);
+        let mut pending = PendingRequests::default();
+        assert!(take_pending_requests(&mut rx, &mut rx_cancel, &mut pending).is_continue());
+        assert!(pending.senders.is_empty());
+        assert!(matches!(pending.take(0), Err(true)));
+    }
+    #[test]
+    fn cancelled_request() {
+        let (tx_to_reader, rx) = mpsc::channel(10);
+        let (tx_cancel, rx_cancel) = mpsc::unbounded_channel();
+        let (tx, mut rx_for_handler) = mpsc::channel(10);
+        let (sender1, rx1) = oneshot::channel();
+        let (sender2, mut rx2) = oneshot::channel();
+        tx_to_reader.try_send(MsgToReader::new(1, sender1)).unwrap();
+        tx_to_reader.try_send(MsgToReader::new(2, sender2)).unwrap();
+        drop(rx1);
+        tx_cancel.send(1).unwrap();
+        let mut input = response(1, "one");
+        input.extend(response(2, "two"));
+        readloop(input.as_slice(), rx, rx_cancel, tx);
+        assert_eq!(rx2.try_recv().unwrap(), Ok(Value::from("two")));
+        // response of the cancelled one is not an error.
+        assert!(matches!(rx_for_handler.try_recv(), Ok(MsgForHandler::Disconnected(_))));
+    }
+}
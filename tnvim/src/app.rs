@@ -9,4 +9,6 @@ use crate::nvim;
 pub struct App {
     pub nvimdata: RefCell::<nvim::Data>,
     pub terminal: Terminal,
+    // wakes up the ticker, when there is a new deadline.
+    pub ticker: tokio::sync::Notify,
 }
@@ -0,0 +1,116 @@
+use crate::terminal::ColorDepth;
+
+/// options given on command line.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// use these colors, instead of what was detected.
+    pub colors: Option<ColorDepth>,
+    pub cmdline: CmdlinePlacement,
+    pub wildmenu: WildmenuLayout,
+    /// show scrolling of windows in a few steps.
+    pub smooth_scroll: bool,
+    /// scrollbar at right edge of windows.
+    pub scrollbar: bool,
+    /// marks of extmarks on the scrollbar.
+    pub scrollbar_marks: bool,
+    pub bell: BellMode,
+    pub visual_bell: BellMode,
+}
+
+/// when bell of nvim rings.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BellMode {
+    #[default]
+    Always,
+    /// only if terminal has focus, so that tnvims in background stay silent.
+    Focused,
+    Never,
+}
+
+impl BellMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "focused" => Some(Self::Focused),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// where cmdline is drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CmdlinePlacement {
+    /// last rows of screen, like nvim does.
+    #[default]
+    Bottom,
+    /// box in the middle of screen.
+    Center,
+}
+
+/// how items of wildmenu are laid out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WildmenuLayout {
+    /// in a row, like nvim does.
+    #[default]
+    Horizontal,
+    /// one item per row.
+    Vertical,
+}
+
+impl Config {
+    /// Take out the options from args. Rest of the args are files to edit.
+    /// Everything after `--` is a file.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<(Self, Vec<String>), String> {
+        let mut config = Self::default();
+        let mut files = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                files.extend(args);
+                break;
+            }
+            let Some(option) = arg.strip_prefix("--") else {
+                files.push(arg);
+                continue;
+            };
+            let (name, value) = option.split_once('=').unwrap_or((option, ""));
+            match name {
+                "colors" => {
+                    let depth = ColorDepth::from_name(value)
+                        .ok_or_else(|| format!("--colors: expected truecolor, 256 or 16, got '{value}'"))?;
+                    config.colors = Some(depth);
+                },
+                "cmdline" => {
+                    config.cmdline = match value {
+                        "bottom" => CmdlinePlacement::Bottom,
+                        "center" => CmdlinePlacement::Center,
+                        _ => return Err(format!("--cmdline: expected bottom or center, got '{value}'")),
+                    };
+                },
+                "wildmenu" => {
+                    config.wildmenu = match value {
+                        "horizontal" => WildmenuLayout::Horizontal,
+                        "vertical" => WildmenuLayout::Vertical,
+                        _ => return Err(format!("--wildmenu: expected horizontal or vertical, got '{value}'")),
+                    };
+                },
+                "smooth-scroll" => config.smooth_scroll = true,
+                "scrollbar" => {
+                    config.scrollbar = true;
+                    config.scrollbar_marks = match value {
+                        "" => false,
+                        "marks" => true,
+                        _ => return Err(format!("--scrollbar: expected nothing or marks, got '{value}'")),
+                    };
+                },
+                "bell" | "visual-bell" => {
+                    let mode = BellMode::from_name(value)
+                        .ok_or_else(|| format!("--{name}: expected always, focused or never, got '{value}'"))?;
+                    if name == "bell" { config.bell = mode; } else { config.visual_bell = mode; }
+                },
+                _ => return Err(format!("unknown option: {arg}")),
+            }
+        }
+        return Ok((config, files));
+    }
+}
@@ -8,14 +8,22 @@ use terminal::Terminal;
 use tokio::runtime::LocalRuntime;
 use crate::app::App;
 mod app;
+mod config;
 mod nvim;
 mod term;
+mod ticker;
 
 fn attach(nvim: &impl Nvimapi,w: u16, h: u16) {
     use nvimapi::NvimapiNr;
-    nvim.nr().ui_attach(w.into(), h.into(), nvimapi::Pairs::from_iter2([
+    nvim::check(nvim.nr().ui_attach(w.into(), h.into(), nvimapi::Pairs::from_iter2([
         (nvimapi::UiOptions::ExtLinegrid, true),
-    ])).unwrap();
+        (nvimapi::UiOptions::ExtMultigrid, true),
+        (nvimapi::UiOptions::ExtPopupmenu, true),
+        (nvimapi::UiOptions::ExtCmdline, true),
+        (nvimapi::UiOptions::ExtMessages, true),
+        (nvimapi::UiOptions::ExtTabline, true),
+        (nvimapi::UiOptions::ExtWildmenu, true),
+    ])), "ui_attach");
 }
 const TERM_INPUT_BUFFER_SIZE :usize = 5;
 pub fn server() -> String {
@@ -24,25 +32,39 @@ pub fn server() -> String {
     socket_file
 }
 pub fn main(mut args: std::env::Args) {
-    let app = App::default();
+    args.next(); //program name
+    let (config, files) = match config::Config::parse(args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("tnvim: {e}");
+            std::process::exit(2);
+        },
+    };
+    let mut app = App::default();
+    app.terminal.caps = terminal::Capabilities::detect(config.colors);
+    app.nvimdata.get_mut().config = config;
     setup(&app.terminal);
     let rt = LocalRuntime::new().unwrap();
     let rt = Rc::new(rt);
     let enter = rt.enter();
-    args.next(); //program name
-    rt.block_on(main_async(rt.clone(), app, args));
+    rt.block_on(main_async(rt.clone(), app, files));
     drop(enter);
 }
-async fn main_async(rt: Rc<LocalRuntime>, _app: App, args: std::env::Args) {
+async fn main_async(rt: Rc<LocalRuntime>, app: App, files: Vec<String>) {
     debug!("hello world");
-    let app = Rc::new(App::default());
-    let (starter, nvim,) = start_nvim_manager(app.clone(), rt.clone(), args);
-    rt.spawn_local(term::input_from_term(app, nvim));
+    let app = Rc::new(app);
+    let (starter, nvim,) = start_nvim_manager(app.clone(), rt.clone(), files);
+    rt.spawn_local(ticker::run(app.clone()));
+    rt.spawn_local(term::input_from_term(app.clone(), nvim));
     starter.await;
+    // input decides what happens next.
+    if app.nvimdata.borrow().server_gone {
+        core::future::pending::<()>().await;
+    }
     before_exit();
 }
 
-fn start_nvim_manager(app: Rc<App>, rt: Rc<LocalRuntime>, mut args: std::env::Args) -> (impl Future, impl Nvimapi) {
+fn start_nvim_manager(app: Rc<App>, rt: Rc<LocalRuntime>, files: Vec<String>) -> (impl Future, impl Nvimapi) {
     use nvimapi::NvimapiNr;
     let socket_path = server();
     let stream = UnixStream::connect(&socket_path);
@@ -66,17 +88,23 @@ fn start_nvim_manager(app: Rc<App>, rt: Rc<LocalRuntime>, mut args: std::env::Ar
         };
     let (task, nvim) = nvimapi::manager::start(app, rt, stream.try_clone().unwrap(), stream);
     let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+    let mut args = files.into_iter();
     if let Some(arg) = args.next() {
-        if !new_nvim { nvim.nr().command("tabnew").unwrap(); }
-        let file = cwd.join(arg);
-        nvim.nr().command(&format!("edit {}", file.display())).unwrap();
+        if !new_nvim { nvim::check(nvim.nr().command("tabnew"), "tabnew"); }
+        edit_file(&nvim, &cwd, &arg);
     }
     for arg in args {
-        let file = cwd.join(arg);
-        nvim.nr().command(&format!("edit {}", file.display())).unwrap();
+        edit_file(&nvim, &cwd, &arg);
     }
     (task,nvim)
 }
+// Relative to where tnvim was started, nvim may be in some other directory. Name is escaped, so
+// spaces and such are part of it.
+fn edit_file(nvim: &impl Nvimapi, cwd: &std::path::Path, arg: &str) {
+    use nvimapi::NvimapiNr;
+    let file = cwd.join(arg).to_string_lossy().into_owned();
+    nvim::check(nvim.nr().exec_lua("vim.cmd('edit ' .. vim.fn.fnameescape(...))", (file,)), "edit");
+}
 
 #[allow(clippy::zombie_processes)]
 fn start_nvim(socket_path: &str) {
@@ -94,11 +122,22 @@ pub fn exit() {
     before_exit();
     std::process::exit(0);
 }
+/// Start tnvim again in place of this one, with same arguments. It connects to the server, or
+/// starts a new one.
+pub fn reconnect() -> ! {
+    use std::os::unix::process::CommandExt as _;
+    before_exit();
+    let exe = std::env::current_exe().unwrap_or_else(|_| "tnvim".into());
+    let e = Command::new(exe).args(std::env::args_os().skip(1)).exec();
+    eprintln!("tnvim: failed to start again: {e}");
+    std::process::exit(1);
+}
 fn before_exit() {
     let _ = terminal::disable_kitty_keyboard_protocol();
     let _ = terminal::disable_bracketed_paste();
     let _ = terminal::disable_focus_events();
     let _ = terminal::disable_mouse_events();
+    terminal::restore_cursor();
     terminal::disable_raw_mode();
     terminal::leave_alternate_screen();
 }
@@ -106,6 +145,9 @@ fn before_exit() {
 fn setup(term: &Terminal) {
     setup_exit();
     set_panic_hook();
+    setup_terminal(term);
+}
+fn setup_terminal(term: &Terminal) {
     term.enter_alternate_screen().unwrap();
     term.enable_raw_mode().unwrap();
     term.enable_mouse_events().unwrap();
@@ -114,6 +156,16 @@ fn setup(term: &Terminal) {
     term.enable_kitty_keyboard_protocol().unwrap();
 }
 
+/// Give terminal back to shell and stop, like nvim does on :suspend. Returns after SIGCONT, with
+/// terminal set up again.
+pub fn suspend(term: &Terminal) {
+    use nix::sys::signal;
+    before_exit();
+    // default action stops the process, raise returns once it's continued.
+    signal::raise(signal::SIGTSTP).unwrap();
+    setup_terminal(term);
+}
+
 fn set_panic_hook() {
     let hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
@@ -8,7 +8,18 @@ use crate::app::App;
 
 async fn redraw_notification(app: &App, nvim: &impl Nvimapi, redraw: Vec<UiEvent>) {
     for event in redraw {
+        let flush = matches!(event, UiEvent::Flush(_));
         handle_uievent(app, nvim, event).await;
+        app.nvimdata.borrow_mut().in_batch = !flush;
+    }
+}
+/// Result of a call to nvim, which tnvim can go on without. Connection being gone is shown by
+/// disconnected, so that's left alone. Other errors are logged.
+pub(crate) fn check<T>(result: nvimapi::error::Result<T>, call: &str) {
+    match result {
+        Ok(_) => {},
+        Err(e) if e.disconnected() => debug!("{call}: {e}"),
+        Err(e) => warn!("{call}: {e}"),
     }
 }
 fn value_get<'v>(map: &'v Value, key: &str) -> Option<&'v Value> {
@@ -39,6 +50,18 @@ impl Handler for App {
                     let _h= value_get(size, "height").unwrap().as_i64().unwrap();
                     // nvim.nr().ui_try_resize(w, h).unwrap();
                 }
+                else if name.starts_with("tnvim.tab") {
+                    tabline::on_notification(self, nvim, &name, args);
+                }
+                else if name == "tnvim.namespaces" {
+                    scrollbar::on_namespaces(self, args);
+                }
+                else if name == "tnvim.bell" {
+                    bell::on_notification(self, args);
+                }
+                else if name == "tnvim.exiting" {
+                    self.nvimdata.borrow_mut().exiting = true;
+                }
             },
         }
 
@@ -52,8 +75,17 @@ impl Handler for App {
         debug!("init");
         let (w,h) = self.terminal.size().unwrap();
         self.nvimdata.borrow_mut().ui_size = data::Size { w, h };
+        let (w, h) = self.nvimdata.borrow().nvim_request_size();
         crate::attach(nvim, w, h);
         self.nvimdata.borrow_mut().attached = true;
+        tabline::query_tabs(nvim);
+        tabline::watch_leave(nvim);
+        gone::watch_exit(nvim);
+    }
+
+    async fn disconnected(&self, reason: Box<nvimapi::error::Error>) {
+        debug!("disconnected: {reason}");
+        gone::on_disconnected(self);
     }
 }
 
@@ -62,6 +94,21 @@ mod unencounteredevents;
 #[allow(unused_variables)]
 mod encounteredevents;
 mod implementedevents;
+mod render;
+mod gridline;
+mod text;
+pub(crate) mod popupmenu;
+pub(crate) mod cmdline;
+pub(crate) mod messages;
+pub(crate) mod tabline;
+pub(crate) mod wildmenu;
+mod floats;
+pub(crate) mod viewport;
+pub(crate) mod scrollbar;
+mod busy;
+mod bell;
+mod options;
+pub(crate) mod gone;
 async fn handle_uievent(this: &App, nvim: &impl Nvimapi, event: UiEvent) {
     use unencounteredevents::*;
     use encounteredevents::*;
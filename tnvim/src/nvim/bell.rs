@@ -0,0 +1,72 @@
+// bell and visual_bell of nvim. Whether they ring is set by --bell and --visual-bell, and can be
+// changed later with tnvim.bell notification.
+use std::time::{Duration, Instant};
+use rmpv::Value;
+use suffixes::CastIt as _;
+use crate::{app::App, config::BellMode, terminal::Terminal};
+use super::{Data, data::UNKNOWN_HL};
+
+// how long screen stays inverted.
+const FLASH: Duration = Duration::from_millis(100);
+
+impl Data {
+    /// whether a bell of this mode rings in this tnvim.
+    pub fn should_ring(&self, mode: BellMode) -> bool {
+        match mode {
+            BellMode::Always => true,
+            BellMode::Focused => !self.unfocused,
+            BellMode::Never => false,
+        }
+    }
+    /// Print surface with fg and bg swapped. Render doesn't print till the flash is over, then
+    /// everything is printed again from surface.
+    pub fn flash(&mut self, term: &Terminal, now: Instant) {
+        if self.layout_dirty {
+            self.compose();
+        }
+        let (rows, cols) = self.surface.size();
+        for row in 0..rows {
+            self.fix_wide_cells(row);
+            let mut col = 0;
+            while col < cols {
+                let start = col;
+                let hl = self.surface[(row, col)].hl;
+                let mut run = String::new();
+                while col < cols && self.surface[(row, col)].hl == hl {
+                    run.push_str(self.surface[(row, col)].char_.as_str());
+                    col += 1;
+                }
+                term.move_cursor(start.u16(), row.u16()).unwrap();
+                self.set_hl_style(hl, true, term);
+                term.print(&run).unwrap();
+            }
+        }
+        self.invalidate_front();
+        self.current_hl_id = UNKNOWN_HL;
+        self.flash_until = Some(now + FLASH);
+    }
+    /// end the flash, if it's time. True if screen is to be rendered again.
+    pub fn step_flash(&mut self, now: Instant) -> bool {
+        if self.flash_until.is_none_or(|until| now < until) { return false }
+        self.flash_until = None;
+        return true;
+    }
+}
+
+/// tnvim.bell, with which bell ("bell" or "visual_bell") and its mode.
+/// Like `:call rpcnotify(0, 'tnvim.bell', 'bell', 'focused')`.
+pub fn on_notification(app: &App, args: Value) {
+    let Value::Array(args) = args else {return};
+    let mut args = args.iter().map(|arg| arg.as_str().unwrap_or(""));
+    let (Some(kind), Some(mode)) = (args.next(), args.next()) else {return};
+    let Some(mode) = BellMode::from_name(mode) else {
+        log::warn!("tnvim.bell: unknown mode {mode}");
+        return;
+    };
+    let mut data = app.nvimdata.borrow_mut();
+    match kind {
+        "bell" => data.config.bell = mode,
+        "visual_bell" => data.config.visual_bell = mode,
+        _ => log::warn!("tnvim.bell: unknown bell {kind}"),
+    }
+}
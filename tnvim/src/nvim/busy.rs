@@ -0,0 +1,52 @@
+// nvim is busy between busy_start and busy_stop. Cursor is hidden then, and if it goes on for a
+// while, a spinner is shown at top right.
+use std::time::{Duration, Instant};
+use suffixes::CastIt as _;
+use super::{Data, data::{Position, BUSY_GRID, CMDLINE_ZINDEX}};
+
+// spinner is not shown for short ones, it would just flicker.
+const DELAY: Duration = Duration::from_millis(500);
+const FRAME: Duration = Duration::from_millis(100);
+const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+#[derive(Debug)]
+pub struct Busy {
+    // when the spinner changes next. First time it's when it's shown.
+    pub next_frame: Instant,
+    // None till spinner is shown.
+    pub frame: Option<usize>,
+}
+
+impl Busy {
+    pub fn new(now: Instant) -> Self {
+        Self { next_frame: now + DELAY, frame: None }
+    }
+}
+
+impl Data {
+    pub fn draw_busy(&mut self) {
+        let cols = self.surface.cols();
+        let Some(frame) = self.busy.as_ref().and_then(|b| b.frame) else {
+            self.hide_overlay(BUSY_GRID);
+            return;
+        };
+        let hl = self.hl_group("NormalFloat").unwrap_or(0);
+        let line = self.widths.text_cells(&format!(" {} busy ", FRAMES[frame]), hl);
+        let width = line.len().min(cols);
+        let pos = Position::new((cols - width).u16(), 0);
+        // over messages and cmdline, it's the only thing changing while nvim is busy.
+        self.set_overlay(BUSY_GRID, pos, width, vec![line], hl, CMDLINE_ZINDEX + 2);
+    }
+    /// next frame of spinner, if it's time. True if something is to be rendered.
+    pub fn step_busy(&mut self, now: Instant) -> bool {
+        let Some(busy) = &mut self.busy else {return false};
+        if now < busy.next_frame { return false }
+        busy.frame = Some(busy.frame.map_or(0, |f| (f + 1) % FRAMES.len()));
+        busy.next_frame = now + FRAME;
+        self.draw_busy();
+        return true;
+    }
+    pub fn busy_deadline(&self) -> Option<Instant> {
+        self.busy.as_ref().map(|b| b.next_frame)
+    }
+}
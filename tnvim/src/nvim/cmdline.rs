@@ -0,0 +1,159 @@
+use rmpv::Value;
+use suffixes::CastIt as _;
+use crate::config::CmdlinePlacement;
+use super::{Data, data::{Cell, GridType, Position, Size, CMDLINE_GRID, CMDLINE_ZINDEX}, text::{self, Widths}};
+
+/// text with the hl id (attr id of hl_attr_define) it's drawn with.
+pub type Chunk = (u16, String);
+
+#[derive(Debug, Default)]
+pub struct Cmdline {
+    // nested cmdlines, like <C-r>= inside :. Index is level - 1, last one is shown.
+    pub levels: Vec<CmdlineLevel>,
+    // previous lines of a multiline command, like :lua <<EOF. Shown above the cmdline.
+    pub block: Vec<Vec<Chunk>>,
+    // where the cursor is on screen, while cmdline is shown.
+    pub cursor: Option<Position>,
+    // screen position of the first cell of content. popupmenu anchors to it.
+    pub content_start: Option<Position>,
+}
+#[derive(Debug, Default)]
+pub struct CmdlineLevel {
+    pub content: Vec<Chunk>,
+    // byte position of cursor in content.
+    pub pos: usize,
+    pub firstc: String,
+    pub prompt: String,
+    pub indent: usize,
+    // char shown at cursor while waiting for rest of it, like after <C-v>. shift says if the
+    // text after it is moved right, or it's drawn over.
+    pub special: Option<(String, bool)>,
+}
+
+pub fn chunks_from_value(content: Vec<Value>) -> Vec<Chunk> {
+    content.into_iter().map(|chunk| {
+        let Value::Array(chunk) = chunk else {unreachable!()};
+        let mut chunk = chunk.into_iter();
+        let hl = chunk.next().and_then(|v| v.as_u64()).unwrap_or(0).u16();
+        let text = match chunk.next() {
+            Some(Value::String(s)) => s.into_str().unwrap_or_default(),
+            _ => String::new(),
+        };
+        (hl, text)
+    }).collect()
+}
+
+impl CmdlineLevel {
+    /// cells of the whole line, and index of cell the cursor is on.
+    fn cells(&self, widths: Widths, base_hl: u16) -> (Vec<Cell>, usize) {
+        let mut cells = widths.text_cells(&self.firstc, base_hl);
+        cells.extend(widths.text_cells(&self.prompt, base_hl));
+        cells.extend((0..self.indent).map(|_| Cell::new(" ", base_hl)));
+        let mut cursor = None;
+        let mut offset = 0;
+        for (hl, text) in &self.content {
+            let hl = if *hl == 0 { base_hl } else { *hl };
+            if cursor.is_none() && self.pos >= offset && self.pos < offset + text.len() && text.is_char_boundary(self.pos - offset) {
+                let (before, after) = text.split_at(self.pos - offset);
+                cells.extend(widths.text_cells(before, hl));
+                cursor = Some(cells.len());
+                cells.extend(widths.text_cells(after, hl));
+            } else {
+                cells.extend(widths.text_cells(text, hl));
+            }
+            offset += text.len();
+        }
+        let cursor = cursor.unwrap_or(cells.len());
+        if let Some((c, shift)) = &self.special {
+            let special = widths.text_cells(c, base_hl);
+            let replaced = if *shift { 0 } else { special.len().min(cells.len() - cursor) };
+            cells.splice(cursor..cursor + replaced, special);
+        }
+        return (cells, cursor);
+    }
+}
+
+impl Data {
+    /// Build cmdline overlay grid from self.cmdline.
+    pub fn draw_cmdline(&mut self) {
+        let (screen_rows, screen_cols) = self.surface.size();
+        let Some(level) = self.cmdline.levels.last() else {
+            self.cmdline.cursor = None;
+            self.cmdline.content_start = None;
+            self.hide_overlay(CMDLINE_GRID);
+            self.draw_wildmenu();
+            return;
+        };
+        if screen_rows == 0 || screen_cols == 0 { return }
+        let base_hl = self.hl_group("MsgArea").unwrap_or(0);
+        let centered = self.config.cmdline == CmdlinePlacement::Center && screen_rows >= 3 && screen_cols >= 12;
+        // space for text, without border.
+        let width =
+            if centered { (screen_cols * 3 / 5).max(40).min(screen_cols - 2) }
+            else { screen_cols };
+
+        let mut lines: Vec<Vec<Cell>> = Vec::new();
+        for block_line in &self.cmdline.block {
+            let mut cells = Vec::new();
+            for (hl, text) in block_line {
+                let hl = if *hl == 0 { base_hl } else { *hl };
+                cells.extend(self.widths.text_cells(text, hl));
+            }
+            let len = cells.len();
+            lines.extend(text::wrap(cells, width, len, base_hl).0);
+        }
+        let (cells, cursor) = level.cells(self.widths, base_hl);
+        let content_start = self.widths.text_width(&level.firstc) + self.widths.text_width(&level.prompt) + level.indent;
+        let content_start = (content_start.min(width.saturating_sub(1)), lines.len());
+        let (rows, (cursor_row, cursor_col)) = text::wrap(cells, width, cursor, base_hl);
+        let cursor_row = cursor_row + lines.len();
+        lines.extend(rows);
+
+        // when it doesn't fit, the lines above go away.
+        let border = if centered { 1 } else { 0 };
+        let max_lines = screen_rows - 2 * border;
+        let skipped = lines.len().saturating_sub(max_lines);
+        let lines = &lines[skipped..];
+        let (height, box_width) = (lines.len() + 2 * border, width + 2 * border);
+        let pos =
+            if centered { Position::new(((screen_cols - box_width) / 2).u16(), ((screen_rows - height) / 3).u16()) }
+            else { Position::new(0, (screen_rows - height).u16()) };
+
+        let mut grid_cells = grid::Grid::init(height, box_width, Cell::new(" ", base_hl));
+        for (row, line) in lines.iter().enumerate() {
+            for (col, cell) in line.iter().enumerate() {
+                grid_cells[(row + border, col + border)] = cell.clone();
+            }
+        }
+        if centered {
+            let hl = self.hl_group("FloatBorder").unwrap_or(base_hl);
+            let last_row = height - 1;
+            let last_col = box_width - 1;
+            for col in 1..last_col {
+                grid_cells[(0, col)] = Cell::new("─", hl);
+                grid_cells[(last_row, col)] = Cell::new("─", hl);
+            }
+            for row in 1..last_row {
+                grid_cells[(row, 0)] = Cell::new("│", hl);
+                grid_cells[(row, last_col)] = Cell::new("│", hl);
+            }
+            grid_cells[(0, 0)] = Cell::new("╭", hl);
+            grid_cells[(0, last_col)] = Cell::new("╮", hl);
+            grid_cells[(last_row, 0)] = Cell::new("╰", hl);
+            grid_cells[(last_row, last_col)] = Cell::new("╯", hl);
+        }
+
+        let to_screen = |col: usize, row: usize| {
+            let row = (row - skipped.min(row) + border).min(height - 1 - border);
+            Position::new(pos.col + (col.min(width - 1) + border).u16(), pos.row + row.u16())
+        };
+        self.cmdline.cursor = Some(to_screen(cursor_col, cursor_row));
+        self.cmdline.content_start = Some(to_screen(content_start.0, content_start.1));
+        let g = self.grid_mut(CMDLINE_GRID);
+        g.cells = grid_cells;
+        g.size = Size { w: box_width.u16(), h: height.u16() };
+        self.set_layout(CMDLINE_GRID, GridType::Overlay, pos, CMDLINE_ZINDEX);
+        // it's placed above cmdline.
+        self.draw_wildmenu();
+    }
+}
@@ -1,28 +1,84 @@
 use cheapclone::CheapClone;
 use nvimapi::{Color, uievent::Tabpage};
+use rustc_hash::FxHashMap;
 use serde::Deserialize;
 use suffixes::CastIt;
 use veci1::VecI1;
-use crate::terminal::{CursorShape, Terminal};
+use crate::terminal::{Attribute, Attributes, ColorDepth, CursorShape, Terminal};
+use super::floats::BLENDED_HL_BASE;
 
 #[derive(Default)]
 pub struct Data {
     pub color_set: ColorSet,
     pub hl_attrs: VecI1<RgbAttrs>,
+    // made by mixing hls of floats with 'winblend' and what's under them.
+    pub blended_hl: super::floats::BlendedHl,
     pub cursor: Cursor,
     pub nvim_size: Size,
     pub ui_size: Size,
+    // final frame, composited from all the visible grids.
     pub surface: grid::Grid<Cell>,
+    // what the terminal is showing right now.
+    pub front: grid::Grid<Cell>,
+    // which grid a cell of surface came from.
+    pub owner: grid::Grid<GridId>,
+    pub grids: FxHashMap<GridId, Grid>,
+    // a grid was placed, hidden or destroyed since surface was last composited.
+    pub layout_dirty: bool,
+    // builtin highlight group names to hl id, from hl_group_set.
+    pub hl_groups: FxHashMap<String, u16>,
+    pub popupmenu: Option<super::popupmenu::Popupmenu>,
+    pub cmdline: super::cmdline::Cmdline,
+    pub wildmenu: Option<super::wildmenu::Wildmenu>,
+    pub messages: super::messages::Messages,
+    pub tabline: Option<super::tabline::Tabline>,
+    // of window grids, from win_viewport.
+    pub viewports: FxHashMap<GridId, super::viewport::Viewport>,
+    pub scroll_anim: Option<super::viewport::ScrollAnim>,
+    pub extmarks: FxHashMap<GridId, super::scrollbar::Marks>,
+    // (grid, (ns_id, mark_id), row) of win_extmark, till topline of the window is known.
+    pub pending_extmarks: Vec<(GridId, (i64, i64), i64)>,
+    // names of namespaces by id, to know what an extmark is for.
+    pub namespaces: FxHashMap<i64, String>,
+    // tabpages of other tnvims, by their pid.
+    pub other_tabs: FxHashMap<u64, Tabpage>,
+    pub showtabline: i64,
+    // rows above nvim's grids, taken by tnvim. nvim's grids are moved down by this much.
+    pub top_rows: u16,
+    // between the first event of a redraw batch and its flush. Screen is half updated.
+    pub in_batch: bool,
+    // between busy_start and busy_stop.
+    pub busy: Option<super::busy::Busy>,
+    // cursor is hidden on terminal.
+    pub cursor_hidden: bool,
+    // terminal told us it lost focus.
+    pub unfocused: bool,
+    // from command line. bell and visual_bell can be changed later by tnvim.bell.
+    pub config: crate::config::Config,
+    // from option_set, by name.
+    pub options: FxHashMap<String, rmpv::Value>,
+    // of chars, from ambiwidth and emoji options.
+    pub widths: super::text::Widths,
+    // nvim sent mouse_off, terminal handles mouse itself.
+    pub mouse_off: bool,
+    // screen is inverted for visual bell till then.
+    pub flash_until: Option<std::time::Instant>,
+    // nvim sent tnvim.exiting, it's going away on purpose.
+    pub exiting: bool,
+    // connection to nvim is gone, though it wasn't exiting.
+    pub server_gone: bool,
     pub current_hl_id: u16,
     pub mode_cursors: Vec<CursorShape>,
     pub my_tab: Option<Tabpage>,
     pub attached: bool,
 }
 // saving char insted of string here makes display of multicodepoint input wrong.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Cell {
     pub char_: Grapheme,
     pub hl: u16,
+    // columns taken on screen. 2 for wide chars, 0 for the empty cell nvim sends after them.
+    pub width: u8,
 }
 #[derive(Clone, Copy)]
 pub struct Char {
@@ -37,9 +93,11 @@ pub enum Grapheme {
 }
 impl Default for Cell {
     fn default() -> Self {
-        Self { char_: Grapheme::space(), hl: 0 }
+        Self { char_: Grapheme::space(), hl: 0, width: 1 }
     }
 }
+// hl id, nvim will not send. Used for cells whose content on terminal is not known.
+pub const UNKNOWN_HL: u16 = u16::MAX;
 
 #[derive(Default, Debug, Clone)]
 pub struct Size {
@@ -52,6 +110,9 @@ pub struct ColorSet {
     pub fg: Color,
     pub bg: Color,
     pub sp: Color,
+    // from palette of terminal, used without termguicolors. None for terminal's default.
+    pub cterm_fg: Option<u8>,
+    pub cterm_bg: Option<u8>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -61,6 +122,7 @@ pub struct RgbAttrs {
     pub background: Option<Color>,
     pub special: Option<Color>,
     pub reverse: bool,
+    pub bold: bool,
     pub italic: bool,
     pub strikethrough: bool,
     pub underline: bool,
@@ -71,25 +133,65 @@ pub struct RgbAttrs {
     pub altfont: bool,
     pub blend: u8,
     pub url: String,
+    // from cterm_attrs, for when termguicolors is off.
+    #[serde(skip)]
+    pub cterm_fg: Option<u8>,
+    #[serde(skip)]
+    pub cterm_bg: Option<u8>,
+    #[serde(skip)]
+    pub cterm_reverse: bool,
 }
-#[allow(dead_code)]
-#[derive(Default, Debug, Clone, Copy)]
+pub type GridId = i64;
+pub const MAIN_GRID: GridId = 1;
+// same as nvim's kZIndexMessages.
+pub const MESSAGE_ZINDEX: i64 = 200;
+// grids drawn by tnvim itself have negative ids, nvim doesn't use them.
+pub const POPUPMENU_GRID: GridId = -1;
+pub const CMDLINE_GRID: GridId = -2;
+pub const MSG_GRID: GridId = -3;
+pub const MSG_PROMPT_GRID: GridId = -4;
+pub const MSG_SHOWMODE_GRID: GridId = -5;
+pub const MSG_STATUS_GRID: GridId = -6;
+pub const MSG_HISTORY_GRID: GridId = -7;
+pub const TABLINE_GRID: GridId = -8;
+pub const WILDMENU_GRID: GridId = -9;
+// not a grid, owner of cells of scrollbars drawn over windows.
+pub const SCROLLBAR_GRID: GridId = -10;
+pub const BUSY_GRID: GridId = -11;
+pub const GONE_GRID: GridId = -12;
+// same as nvim's kZIndexPopupMenu.
+pub const POPUPMENU_ZINDEX: i64 = 100;
+// above messages.
+pub const CMDLINE_ZINDEX: i64 = 250;
+// it's in its own rows, so doesn't matter much.
+pub const TABLINE_ZINDEX: i64 = 1;
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GridType {
     #[default]
     Normal,
+    Float,
     Message,
     Main,
+    // drawn by tnvim for an ext_* ui element.
+    Overlay,
 }
-#[allow(dead_code)]
 #[derive(Debug, Default, Clone)]
 pub struct Grid {
     pub size: Size,
     pub pos: Position,
     pub grid_type: GridType,
+    pub cells: grid::Grid<Cell>,
+    pub shown: bool,
+    // grids with higher zindex are drawn over lower ones. Equal ones are ordered by id.
+    pub zindex: i64,
+    // for floats, pos is worked out from this when composing.
+    pub float: Option<super::floats::FloatPos>,
 }
 #[derive(Debug, Default, Clone)]
 pub struct Cursor {
+    // relative to grid.
     pub pos: Position,
+    pub grid: GridId,
 }
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Position {
@@ -98,24 +200,216 @@ pub struct Position {
 }
 
 impl Position {
-    #[allow(dead_code)]
     pub fn new(col: u16, row: u16,) -> Self {
         Self {col, row}
     }
 }
 
+impl Grid {
+    // keeps the overlapping part, nvim will redraw the rest.
+    pub fn resize(&mut self, w: u16, h: u16) {
+        let mut cells = grid::Grid::<Cell>::new(h.u(), w.u());
+        let rows = h.min(self.size.h).u();
+        let cols = w.min(self.size.w).u();
+        for row in 0..rows {
+            for col in 0..cols {
+                cells[(row, col)] = self.cells[(row, col)].cheap_clone();
+            }
+        }
+        self.cells = cells;
+        self.size = Size { w, h };
+    }
+}
+
 impl Data {
+    pub fn grid_mut(&mut self, grid: GridId) -> &mut Grid {
+        self.grids.entry(grid).or_default()
+    }
+    /// position of top left of grid on surface. nvim's grids are below the rows taken by tnvim.
+    pub fn origin(&self, id: GridId, grid: &Grid) -> Position {
+        if id < 0 { return grid.pos }
+        Position::new(grid.pos.col, grid.pos.row + self.top_rows)
+    }
+    /// position of a cell of grid on the surface. None if it's hidden or out of the screen.
+    pub fn to_screen(&self, grid: GridId, col: u16, row: u16) -> Option<Position> {
+        let g = self.grids.get(&grid)?;
+        if !g.shown { return None }
+        let origin = self.origin(grid, g);
+        let col = origin.col.checked_add(col)?;
+        let row = origin.row.checked_add(row)?;
+        if col.u() >= self.surface.cols() || row.u() >= self.surface.rows() { return None }
+        return Some(Position { col, row });
+    }
+    /// Update a cell of a grid, and of surface if the cell is visible there.
+    pub fn put_cell(&mut self, grid: GridId, col: u16, row: u16, cell: Cell) {
+        if !self.layout_dirty
+            && let Some(spos) = self.to_screen(grid, col, row)
+        {
+            let spos = (spos.row.u(), spos.col.u());
+            // a blended cell depends on cells under it too, it's mixed again in compose.
+            if self.surface[spos].hl >= BLENDED_HL_BASE
+                || (self.owner[spos] == grid && self.hl_blend(cell.hl) > 0)
+            {
+                self.layout_dirty = true;
+            } else if self.owner[spos] == grid {
+                self.surface[spos] = cell.cheap_clone();
+            }
+        }
+        if let Some(g) = self.grids.get_mut(&grid) && let Some(c) = g.cells.get_mut(row, col) {
+            *c = cell;
+        }
+    }
+    pub fn set_layout(&mut self, grid: GridId, grid_type: GridType, pos: Position, zindex: i64) {
+        let g = self.grid_mut(grid);
+        g.grid_type = grid_type;
+        g.pos = pos;
+        g.zindex = zindex;
+        g.shown = true;
+        if grid_type != GridType::Float { g.float = None; }
+        self.layout_dirty = true;
+    }
+    /// grid whose cell is shown at this position of screen.
+    pub fn owner_at(&self, col: u16, row: u16) -> Option<GridId> {
+        if self.layout_dirty { return None }
+        self.owner.get(row, col).copied()
+    }
+    /// Show lines as an overlay grid at pos. Lines are cut or filled with spaces to width.
+    /// Without any lines, it's hidden.
+    pub fn set_overlay(
+        &mut self, grid: GridId, pos: Position, width: usize,
+        lines: Vec<Vec<Cell>>, fill_hl: u16, zindex: i64,
+    ) {
+        if lines.is_empty() || width == 0 {
+            self.hide_overlay(grid);
+            return;
+        }
+        let mut cells = grid::Grid::init(lines.len(), width, Cell::new(" ", fill_hl));
+        for (row, line) in lines.into_iter().enumerate() {
+            for (col, cell) in line.into_iter().take(width).enumerate() {
+                cells[(row, col)] = cell;
+            }
+        }
+        let g = self.grid_mut(grid);
+        g.size = Size { w: cells.cols().u16(), h: cells.rows().u16() };
+        g.cells = cells;
+        self.set_layout(grid, GridType::Overlay, pos, zindex);
+    }
+    pub fn hide_overlay(&mut self, grid: GridId) {
+        if self.grids.remove(&grid).is_some() {
+            self.layout_dirty = true;
+        }
+    }
+    /// rebuild surface from grids, lowest zindex first. Cells with blend are mixed with the ones
+    /// under them.
+    pub fn compose(&mut self) {
+        self.place_floats();
+        if self.blended_hl.is_full() {
+            // old ids may mean something else now.
+            self.blended_hl.clear();
+            self.invalidate_front();
+        }
+        let (rows, cols) = self.surface.size();
+        self.owner = grid::Grid::new(rows, cols);
+        self.surface.fill(Cell::default());
+        let mut order: Vec<(i64, GridId)> = self.grids.iter()
+            .filter(|(_, g)| g.shown)
+            .map(|(id, g)| (g.zindex, *id))
+            .collect();
+        order.sort_unstable();
+        for (_, id) in order {
+            let grid = &self.grids[&id];
+            let origin = self.origin(id, grid);
+            for ((row, col), cell) in grid.cells.indexed_iter() {
+                let srow = origin.row.u() + row;
+                let scol = origin.col.u() + col;
+                if srow >= rows || scol >= cols { continue; }
+                let blend = self.hl_attrs.get(cell.hl.u()).map(|a| a.blend).unwrap_or(0);
+                let below = &mut self.surface[(srow, scol)];
+                if blend > 0 {
+                    let thru = cell.char_.as_str() == " ";
+                    let hl = self.blended_hl.get(&self.hl_attrs, &self.color_set, cell.hl, below.hl, thru);
+                    if thru { below.hl = hl; }
+                    else { *below = Cell { hl, ..cell.cheap_clone() }; }
+                } else {
+                    *below = cell.cheap_clone();
+                }
+                self.owner[(srow, scol)] = id;
+            }
+        }
+        if self.config.scrollbar {
+            self.draw_scrollbars();
+        }
+        self.layout_dirty = false;
+    }
+    /// A wide char whose right half is covered by another grid, or cut off by the edge, can't be
+    /// shown. Same for a right half without its wide char. Replace them with spaces.
+    pub fn fix_wide_cells(&mut self, row: usize) {
+        let cols = self.surface.cols();
+        for col in 0..cols {
+            let cell = &self.surface[(row, col)];
+            let broken = match cell.width {
+                2 => col + 1 >= cols || !self.surface[(row, col + 1)].is_continuation(),
+                0 => col == 0 || self.surface[(row, col - 1)].width != 2,
+                _ => false,
+            };
+            if broken {
+                self.surface[(row, col)] = Cell::new(" ", cell.hl);
+            }
+        }
+    }
+    /// surface is main grid with the rows taken by tnvim above it, but not more than terminal.
+    pub fn resize_surface(&mut self, term: &Terminal) {
+        let mut rows = self.nvim_size.h + self.top_rows;
+        if self.ui_size.h > 0 { rows = rows.min(self.ui_size.h); }
+        self.surface = grid::Grid::new(rows.u(), self.nvim_size.w.u());
+        self.clear_front(term);
+        self.layout_dirty = true;
+    }
+    /// content of terminal is not known anymore, so everything is printed on next render.
+    pub fn invalidate_front(&mut self) {
+        let (rows, cols) = self.surface.size();
+        self.front = grid::Grid::init(rows, cols, Cell::unknown());
+    }
+    /// clear the terminal, so that front is known to be empty.
+    pub fn clear_front(&mut self, term: &Terminal) {
+        self.apply_hl_id_forced(0, term);
+        term.clear_screen().unwrap();
+        let (rows, cols) = self.surface.size();
+        self.front = grid::Grid::new(rows, cols);
+    }
+    /// cursor position on surface.
+    pub fn cursor_screen_pos(&self) -> Position {
+        // nvim doesn't move the cursor to cmdline drawn by us.
+        if let Some(pos) = self.cmdline.cursor { return pos }
+        let cursor = &self.cursor;
+        self.to_screen(cursor.grid, cursor.pos.col, cursor.pos.row).unwrap_or(cursor.pos)
+    }
+    /// url set on the highlight by nvim, empty if there is none.
+    pub fn hl_url(&self, hl_id: u16) -> &str {
+        if hl_id == 0 || hl_id == UNKNOWN_HL { return "" }
+        self.hl_attr(hl_id).map(|attr| attr.url.as_str()).unwrap_or("")
+    }
     pub fn apply_hl_id_forced(&mut self, hl_id: u16, term: &Terminal) {
         self.current_hl_id = hl_id;
+        self.set_hl_style(hl_id, false, term);
+    }
+    /// Set style of hl on terminal. inverted swaps fg and bg once more, for visual bell.
+    pub fn set_hl_style(&self, hl_id: u16, inverted: bool, term: &Terminal) {
         let dbg = self.color_set.bg;
         let dfg = self.color_set.fg;
-        let dsp = self.color_set.sp;
-        if hl_id == 0 {
-            term.set_colors(dbg, dfg).unwrap();
+        let rgb_attr = if hl_id == 0 { None } else { self.hl_attr(hl_id) };
+        // server is shared by other tnvims, its termguicolors may be for some other terminal. One
+        // which can show rgb always gets it, others quantise it if termguicolors asks for rgb.
+        if term.caps.colors != ColorDepth::TrueColor && !self.termguicolors() {
+            self.set_cterm_hl_style(rgb_attr, inverted, term);
             return;
         }
-
-        let rgb_attr = &self.hl_attrs[hl_id];
+        let swap = |(bg, fg)| if inverted { (fg, bg) } else { (bg, fg) };
+        let Some(rgb_attr) = rgb_attr else {
+            let (bg, fg) = swap((dbg, dfg));
+            term.set_style(bg, fg, None, Attributes::default()).unwrap();
+            return;
+        };
         
         let bg = rgb_attr.background.unwrap_or(dbg);
         let fg = rgb_attr.foreground.unwrap_or(dfg);
@@ -125,15 +419,55 @@ impl Data {
             } else {
                 (bg, fg)
             };
+        let (bg, fg) = swap((bg, fg));
         // debug!("fg: {fg:?}, bg: {bg:?}");
-        let _sp = rgb_attr.special.unwrap_or(dsp);
-        term.set_colors(bg, fg,).unwrap();
+        let attributes = attributes(rgb_attr);
+        let underlined = rgb_attr.underline || rgb_attr.undercurl || rgb_attr.underdouble
+            || rgb_attr.underdotted || rgb_attr.underdashed;
+        // without special, terminal uses fg for underline anyways.
+        let sp = rgb_attr.special.filter(|_| underlined);
+        term.set_style(bg, fg, sp, attributes).unwrap();
+    }
+    // colours of palette, nvim's colorscheme picked them for cterm.
+    fn set_cterm_hl_style(&self, attr: Option<&RgbAttrs>, inverted: bool, term: &Terminal) {
+        let (mut bg, mut fg) = (self.color_set.cterm_bg, self.color_set.cterm_fg);
+        let mut reverse = inverted;
+        if let Some(attr) = attr {
+            bg = attr.cterm_bg.or(bg);
+            fg = attr.cterm_fg.or(fg);
+            reverse ^= attr.cterm_reverse;
+        }
+        let (bg, fg) = if reverse { (fg, bg) } else { (bg, fg) };
+        let attributes = attr.map(attributes).unwrap_or_default();
+        term.set_cterm_style(bg, fg, attributes).unwrap();
     }
     pub fn apply_hl_id(&mut self, hl_id: u16, term: &Terminal) {
         if self.current_hl_id == hl_id {return}
         self.apply_hl_id_forced(hl_id, term);
     }
 }
+// bold, italic and such of hl.
+fn attributes(rgb_attr: &RgbAttrs) -> Attributes {
+    let mut attributes = Attributes::default();
+    for (enabled, attribute) in [
+        (rgb_attr.bold, Attribute::Bold),
+        (rgb_attr.italic, Attribute::Italic),
+        (rgb_attr.strikethrough, Attribute::CrossedOut),
+        (rgb_attr.underline, Attribute::Underlined),
+        (rgb_attr.undercurl, Attribute::Undercurled),
+        (rgb_attr.underdouble, Attribute::DoubleUnderlined),
+        (rgb_attr.underdotted, Attribute::Underdotted),
+        (rgb_attr.underdashed, Attribute::Underdashed),
+    ] {
+        if enabled { attributes.set(attribute); }
+    }
+    return attributes;
+}
+impl PartialEq for Grapheme {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
 impl AsRef<str> for Grapheme {
     fn as_ref(&self) -> &str {
         self.as_str()
@@ -218,8 +552,17 @@ impl Cell {
         Self {
             char_: Grapheme::from(char_),
             hl,
+            width: if char_.is_empty() { 0 } else { 1 },
         }
     }
+    /// for front, where it's not known what terminal is showing.
+    pub fn unknown() -> Self {
+        Self { char_: Grapheme::space(), hl: UNKNOWN_HL, width: 1 }
+    }
+    /// right half of a wide char.
+    pub fn is_continuation(&self) -> bool {
+        self.width == 0
+    }
 }
 
 
@@ -229,8 +572,17 @@ mod app {
 
 use crate::App;
 impl App {
-    pub fn set_cursor(&self, col: u16, row: u16) {
+    /// Render changes made outside of redraw events, like by timers or input.
+    pub fn redraw(&self) {
+        let mut data = self.nvimdata.borrow_mut();
+        // flush at end of the batch will do it.
+        if data.in_batch { return }
+        data.render(&self.terminal);
+        self.terminal.flush().unwrap();
+    }
+    pub fn set_cursor(&self, grid: super::GridId, col: u16, row: u16) {
         let mut data = self.nvimdata.borrow_mut();
+        data.cursor.grid = grid;
         data.cursor.pos.col = col;
         data.cursor.pos.row = row;
         drop(data);
@@ -0,0 +1,136 @@
+// Placing floating windows by their anchor, and mixing colours of floats with 'winblend' with what is
+// under them.
+use nvimapi::Color;
+use rustc_hash::FxHashMap;
+use suffixes::CastIt as _;
+use veci1::VecI1;
+use super::{Data, data::{ColorSet, GridId, Position, RgbAttrs}};
+
+// hl ids from here are made by tnvim for blended cells. nvim doesn't define that many.
+pub const BLENDED_HL_BASE: u16 = 0xC000;
+// UNKNOWN_HL is just after.
+const MAX_BLENDED: usize = (u16::MAX - BLENDED_HL_BASE) as usize;
+
+/// which corner of float is at the anchor position.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    #[default]
+    NW,
+    NE,
+    SW,
+    SE,
+}
+/// where nvim wants a float, from win_float_pos. It's placed again when anchor grid moves or float
+/// is resized.
+#[derive(Debug, Default, Clone)]
+pub struct FloatPos {
+    pub corner: Corner,
+    pub anchor_grid: GridId,
+    pub row: f64,
+    pub col: f64,
+}
+
+impl Corner {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "NE" => Self::NE,
+            "SW" => Self::SW,
+            "SE" => Self::SE,
+            _ => Self::NW,
+        }
+    }
+}
+
+/// hl ids of blended cells, by (hl of float, hl under it, whether the char under shows through).
+#[derive(Debug, Default)]
+pub struct BlendedHl {
+    ids: FxHashMap<(u16, u16, bool), u16>,
+    attrs: Vec<RgbAttrs>,
+}
+
+impl BlendedHl {
+    pub fn attrs(&self, hl_id: u16) -> Option<&RgbAttrs> {
+        self.attrs.get((hl_id - BLENDED_HL_BASE).u())
+    }
+    pub fn is_full(&self) -> bool {
+        self.attrs.len() >= MAX_BLENDED
+    }
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.attrs.clear();
+    }
+    /// hl id for a cell of hl top, drawn over a cell of hl below. thru is when top is a space, then
+    /// the char below is shown, in colours mixed with background of top. Like nvim's hl_blend_attrs.
+    pub fn get(&mut self, hl_attrs: &VecI1<RgbAttrs>, colors: &ColorSet, top: u16, below: u16, thru: bool) -> u16 {
+        if let Some(id) = self.ids.get(&(top, below, thru)) { return *id }
+        if self.is_full() { return top }
+        // below may be blended already, by a lower float.
+        let attrs_of = |hl: u16| if hl >= BLENDED_HL_BASE { self.attrs(hl).cloned() } else { hl_attrs.get(hl.u()).cloned() };
+        let (top_attrs, below_attrs) = (attrs_of(top), attrs_of(below));
+        let ratio = top_attrs.as_ref().map(|a| a.blend).unwrap_or(0).min(100);
+        let (top_fg, top_bg) = resolved(top_attrs.as_ref(), colors);
+        let (below_fg, below_bg) = resolved(below_attrs.as_ref(), colors);
+        let mut attrs = (if thru { below_attrs } else { top_attrs }).unwrap_or_default();
+        attrs.foreground = Some(
+            if thru { mix(ratio, below_fg, top_bg) }
+            else { mix(ratio / 2, below_bg, top_fg) }
+        );
+        attrs.background = Some(mix(ratio, below_bg, top_bg));
+        attrs.reverse = false;
+        attrs.blend = 0;
+        let id = BLENDED_HL_BASE + self.attrs.len().u16();
+        self.attrs.push(attrs);
+        self.ids.insert((top, below, thru), id);
+        return id;
+    }
+}
+
+// fg and bg as shown, with defaults filled in and reverse applied.
+fn resolved(attrs: Option<&RgbAttrs>, colors: &ColorSet) -> (Color, Color) {
+    let Some(attrs) = attrs else { return (colors.fg, colors.bg) };
+    let fg = attrs.foreground.unwrap_or(colors.fg);
+    let bg = attrs.background.unwrap_or(colors.bg);
+    if attrs.reverse { (bg, fg) } else { (fg, bg) }
+}
+
+// ratio percent of a, rest of b.
+fn mix(ratio: u8, a: Color, b: Color) -> Color {
+    let ratio = u32::from(ratio);
+    let channel = |a: u8, b: u8| ((ratio * u32::from(a) + (100 - ratio) * u32::from(b)) / 100).u8();
+    Color { r: channel(a.r, b.r), g: channel(a.g, b.g), b: channel(a.b, b.b) }
+}
+
+impl Data {
+    /// Set position of floats from their anchors. Lower ones first, as a float can be anchored to
+    /// another float.
+    pub fn place_floats(&mut self) {
+        let mut floats: Vec<(i64, GridId)> = self.grids.iter()
+            .filter(|(_, g)| g.float.is_some())
+            .map(|(id, g)| (g.zindex, *id))
+            .collect();
+        floats.sort_unstable();
+        for (_, id) in floats {
+            let g = &self.grids[&id];
+            let Some(float) = &g.float else {continue};
+            let anchor = self.grids.get(&float.anchor_grid).map(|a| a.pos).unwrap_or_default();
+            let mut col = anchor.col.f64() + float.col;
+            let mut row = anchor.row.f64() + float.row;
+            if matches!(float.corner, Corner::NE | Corner::SE) { col -= g.size.w.f64(); }
+            if matches!(float.corner, Corner::SW | Corner::SE) { row -= g.size.h.f64(); }
+            // kept inside the screen, like nvim does.
+            let max_col = self.nvim_size.w.saturating_sub(g.size.w).f64();
+            let max_row = self.nvim_size.h.saturating_sub(g.size.h).f64();
+            let pos = Position::new(col.min(max_col).max(0.0).u16(), row.min(max_row).max(0.0).u16());
+            self.grid_mut(id).pos = pos;
+        }
+    }
+    /// blend of the hl, 0 when it's opaque.
+    pub fn hl_blend(&self, hl_id: u16) -> u8 {
+        self.hl_attr(hl_id).map(|a| a.blend).unwrap_or(0)
+    }
+    /// attributes of hl, nvim's or of a blended cell.
+    pub fn hl_attr(&self, hl_id: u16) -> Option<&RgbAttrs> {
+        if hl_id >= BLENDED_HL_BASE { return self.blended_hl.attrs(hl_id) }
+        self.hl_attrs.get(hl_id.u())
+    }
+}
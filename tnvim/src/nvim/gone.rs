@@ -0,0 +1,51 @@
+// nvim server went away without saying it's exiting, like when it crashed or was killed. tnvim
+// says so, and waits for user to reconnect or quit, instead of vanishing.
+use nvimapi::{Nvimapi, NvimapiNr as _};
+use suffixes::CastIt as _;
+use crate::{app::App, terminal::event::{Event, KeyCode}};
+use super::{Data, data::{Position, CMDLINE_ZINDEX, GONE_GRID}};
+
+const LINES: [&str; 2] = [" nvim server went away ", " r: reconnect   q: quit "];
+
+impl Data {
+    pub fn draw_gone(&mut self) {
+        let (rows, cols) = self.surface.size();
+        let hl = self.hl_group("ErrorMsg").unwrap_or(0);
+        let lines: Vec<_> = LINES.iter().map(|line| self.widths.text_cells(line, hl)).collect();
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0).min(cols);
+        let pos = Position::new(((cols - width) / 2).u16(), (rows.saturating_sub(lines.len()) / 2).u16());
+        // over everything, nothing else is going to change.
+        self.set_overlay(GONE_GRID, pos, width, lines, hl, CMDLINE_ZINDEX + 3);
+    }
+}
+
+/// Ask nvim to send tnvim.exiting when it quits. Then going away is not a surprise.
+pub fn watch_exit(nvim: &impl Nvimapi) {
+    let code = "local group = vim.api.nvim_create_augroup('tnvim_exiting', {}) \
+        vim.api.nvim_create_autocmd('VimLeavePre', {group = group, callback = function() vim.rpcnotify(0, 'tnvim.exiting') end})";
+    super::check(nvim.nr().exec_lua(code, [(); 0]), "watch_exit");
+}
+
+/// connection to nvim is gone. Unless nvim said it's exiting, user is told about it.
+pub fn on_disconnected(app: &App) {
+    let mut data = app.nvimdata.borrow_mut();
+    if data.exiting { return }
+    data.server_gone = true;
+    // a batch cut short won't get its flush.
+    data.in_batch = false;
+    data.busy = None;
+    data.draw_busy();
+    data.draw_gone();
+    drop(data);
+    app.redraw();
+}
+
+/// input from terminal, while server is gone.
+pub fn on_event(event: Event) {
+    let Event::Key(key) = event else {return};
+    match key.code {
+        KeyCode::Char('r') => crate::reconnect(),
+        KeyCode::Char('q') => crate::exit(),
+        _ => {},
+    }
+}
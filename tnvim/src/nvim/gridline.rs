@@ -0,0 +1,171 @@
+use cheapclone::CheapClone as _;
+use nvimapi::uievent;
+use rmpv::Value;
+use suffixes::CastIt as _;
+use super::{Data, data::Cell};
+
+impl Data {
+    /// put cells of a grid_line on its grid. hl_id is carried over from previous line of same
+    /// redraw batch, as nvim leaves it out when it doesn't change.
+    pub fn grid_line(&mut self, line: uievent::GridLine, hl_id: &mut u16) {
+        let row = line.row.u16();
+        let col_start = line.col_start.u16();
+        for (i, cell) in decode_cells(line.data, hl_id).into_iter().enumerate() {
+            self.put_cell(line.grid, col_start + i.u16(), row, cell);
+        }
+    }
+}
+
+/// expand [text, hl_id, repeat] items to cells. nvim sends a wide char as the char followed by an
+/// empty string, so the cell before an empty one is marked wide.
+fn decode_cells(data: Vec<Value>, hl_id: &mut u16) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = Vec::with_capacity(data.len());
+    for cell in data {
+        let Value::Array(cell) = cell else {unreachable!()};
+        let mut items = cell.into_iter();
+        let text = items.next().unwrap();
+        let Value::String(text) = text else {unreachable!()};
+        let text = text.into_str().unwrap_or_else(|| String::from("□"));
+        if let Some(id) = items.next() {
+            *hl_id = id.as_u64().unwrap().u16();
+        }
+        // repeat should be signed as it's possible for neovim to send repeat = 0.
+        let repeat = items.next().map(|v| v.as_i64().unwrap()).unwrap_or(1);
+        let gcell = Cell::new(&text, *hl_id);
+        if gcell.is_continuation() && repeat > 0
+            && let Some(prev) = cells.last_mut() && !prev.is_continuation()
+        {
+            prev.width = 2;
+        }
+        for _ in 0..repeat {
+            cells.push(gcell.cheap_clone());
+        }
+    }
+    return cells;
+}
+
+#[cfg(test)]
+mod tests {
+    use nvimapi::uievent::GridLine;
+    use rmpv::Value;
+    use crate::terminal::Terminal;
+    use super::super::data::{Data, Cell, GridType, Position, MAIN_GRID};
+
+    fn data(w: u16, h: u16) -> Data {
+        let mut data = Data { surface: grid::Grid::new(h.into(), w.into()), ..Data::default() };
+        data.grid_mut(MAIN_GRID).resize(w, h);
+        data.set_layout(MAIN_GRID, GridType::Main, Position::new(0, 0), 0);
+        data.invalidate_front();
+        return data;
+    }
+    fn line(row: i64, col_start: i64, cells: &[(&str, Option<u64>, Option<i64>)]) -> GridLine {
+        let data = cells.iter().map(|&(text, hl, repeat)| {
+            let mut cell = vec![Value::from(text)];
+            if let Some(hl) = hl { cell.push(Value::from(hl)); }
+            if let Some(repeat) = repeat { cell.push(Value::from(repeat)); }
+            Value::Array(cell)
+        }).collect();
+        GridLine { grid: MAIN_GRID, row, col_start, data, wrap: false }
+    }
+    fn put(data: &mut Data, lines: Vec<GridLine>) {
+        let mut hl_id = 0;
+        for line in lines {
+            data.grid_line(line, &mut hl_id);
+        }
+    }
+    fn row_text(data: &Data, row: usize) -> Vec<(String, u8)> {
+        data.surface.iter_row(row)
+            .map(|cell| (cell.char_.as_str().to_owned(), cell.width))
+            .collect()
+    }
+    fn render(data: &mut Data) -> String {
+        let term = Terminal::default();
+        data.render(&term);
+        return String::from_utf8(term.take_output()).unwrap();
+    }
+    fn cells(expected: &[(&str, u8)]) -> Vec<(String, u8)> {
+        expected.iter().map(|&(text, width)| (text.to_owned(), width)).collect()
+    }
+
+    #[test]
+    fn cjk() {
+        let mut data = data(6, 1);
+        put(&mut data, vec![line(0, 0, &[
+            ("中", Some(0), None), ("", None, None),
+            ("文", None, None), ("", None, None),
+            ("a", None, None), (" ", None, None),
+        ])]);
+        data.compose();
+        assert_eq!(row_text(&data, 0), cells(&[("中", 2), ("", 0), ("文", 2), ("", 0), ("a", 1), (" ", 1)]));
+        let out = render(&mut data);
+        // after each wide char, cursor is placed explicitly.
+        assert!(out.contains("中\x1b[1;3H文\x1b[1;5Ha "), "{out:?}");
+    }
+
+    #[test]
+    fn emoji_zwj() {
+        let mut data = data(4, 1);
+        let family = "👨\u{200d}👩\u{200d}👧";
+        put(&mut data, vec![line(0, 0, &[
+            (family, Some(0), None), ("", None, None), ("x", Some(0), Some(2)),
+        ])]);
+        data.compose();
+        assert_eq!(row_text(&data, 0), cells(&[(family, 2), ("", 0), ("x", 1), ("x", 1)]));
+        let out = render(&mut data);
+        assert!(out.contains(&format!("{family}\x1b[1;3Hxx")), "{out:?}");
+    }
+
+    #[test]
+    fn combining_marks() {
+        let mut data = data(3, 1);
+        put(&mut data, vec![line(0, 0, &[
+            ("e\u{301}", Some(0), None), ("a\u{308}\u{323}", None, None), ("z", None, None),
+        ])]);
+        data.compose();
+        assert_eq!(row_text(&data, 0), cells(&[("e\u{301}", 1), ("a\u{308}\u{323}", 1), ("z", 1)]));
+        let out = render(&mut data);
+        assert!(out.contains("e\u{301}a\u{308}\u{323}z"), "{out:?}");
+    }
+
+    #[test]
+    fn narrow_over_wide() {
+        let mut data = data(4, 1);
+        put(&mut data, vec![line(0, 0, &[
+            ("中", Some(0), None), ("", None, None), ("a", None, None), ("b", None, None),
+        ])]);
+        render(&mut data);
+        // only the right half of wide char is replaced.
+        put(&mut data, vec![line(0, 1, &[("c", Some(0), None)])]);
+        let out = render(&mut data);
+        assert_eq!(row_text(&data, 0), cells(&[(" ", 1), ("c", 1), ("a", 1), ("b", 1)]));
+        assert!(out.contains("\x1b[1;1H c"), "{out:?}");
+        assert_eq!(data.front.iter_row(0).cloned().collect::<Vec<_>>(), data.surface.iter_row(0).cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn right_half_changed() {
+        let mut data = data(4, 1);
+        put(&mut data, vec![line(0, 0, &[
+            ("中", Some(0), None), ("", None, None), ("a", None, None), ("b", None, None),
+        ])]);
+        render(&mut data);
+        // wide char stays, its right half and the cell after it change.
+        put(&mut data, vec![line(0, 1, &[("", Some(1), None), ("c", None, None)])]);
+        let out = render(&mut data);
+        let wide = out.find("\x1b[1;1H").zip(out.find("中")).expect(&out);
+        assert!(wide.0 < wide.1, "{out:?}");
+        assert!(out.contains("\x1b[1;3H"), "{out:?}");
+        assert!(!out.contains("\x1b[1;2H"), "{out:?}");
+        assert_eq!(data.front.iter_row(0).cloned().collect::<Vec<_>>(), data.surface.iter_row(0).cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn wide_at_last_column() {
+        let mut data = data(3, 1);
+        data.compose();
+        // right half is cut off by end of grid.
+        data.surface[(0, 2)] = Cell { width: 2, ..Cell::new("中", 0) };
+        render(&mut data);
+        assert_eq!(row_text(&data, 0)[2], (" ".to_owned(), 1));
+    }
+}
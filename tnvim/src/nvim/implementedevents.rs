@@ -4,8 +4,9 @@ use log::{debug, trace};
 use nvimapi::{Nvimapi, Pairs, TryFromValue, uievent};
 use rmpv::Value;
 use serde::Deserialize;
+use std::collections::hash_map::Entry;
 use suffixes::CastIt;
-use crate::{app::App, nvim::data::{RgbAttrs}, };
+use crate::{app::App, nvim::{data::{GridType, MAIN_GRID, MESSAGE_ZINDEX, POPUPMENU_GRID, Position, RgbAttrs, UNKNOWN_HL}, popupmenu, cmdline, messages, tabline, wildmenu, floats, viewport, scrollbar, busy, options}, };
 use nvimapi::Color as NColor;
 use crate::terminal::CursorShape;
 
@@ -25,7 +26,14 @@ pub(super) async fn do_default_colors_set(app: &App, _: &impl Nvimapi, events: V
     data.color_set.fg = fg;
     data.color_set.bg = bg;
     data.color_set.sp = sp;
-    data.apply_hl_id_forced(0, &app.terminal);
+    // 0 is terminal's default, others are one more than palette index.
+    data.color_set.cterm_fg = (colors.cterm_fg > 0).then(|| (colors.cterm_fg - 1).u8());
+    data.color_set.cterm_bg = (colors.cterm_bg > 0).then(|| (colors.cterm_bg - 1).u8());
+    // cells with default colors have to be printed again.
+    data.invalidate_front();
+    // blended ones were mixed with old defaults.
+    data.blended_hl.clear();
+    data.layout_dirty = true;
     drop(data);
 }
 pub(super) async fn do_hl_attr_define(this: &App, _: &impl Nvimapi, events: Vec<uievent::HlAttrDefine>) {
@@ -33,6 +41,8 @@ pub(super) async fn do_hl_attr_define(this: &App, _: &impl Nvimapi, events: Vec<
     let rgb_attrs = &mut data.hl_attrs;
 
     let last_id = events.last().unwrap().id.u();
+    // an existing id is redefined (colorscheme changed), cells using it have to be printed again.
+    let redefined = events.iter().any(|e| e.id.u() <= rgb_attrs.len());
     
     if rgb_attrs.len() < last_id {
         rgb_attrs.resize(last_id, RgbAttrs::default());
@@ -40,27 +50,66 @@ pub(super) async fn do_hl_attr_define(this: &App, _: &impl Nvimapi, events: Vec<
     }
 
     for hl_define in events {
-        let rgb_attr = RgbAttrs::deserialize(Value::Map(hl_define.rgb_attrs.inner)).unwrap();
+        let mut rgb_attr = RgbAttrs::deserialize(Value::Map(hl_define.rgb_attrs.inner)).unwrap();
+        for (key, value) in hl_define.cterm_attrs.inner {
+            match key.as_str() {
+                Some("foreground") => rgb_attr.cterm_fg = value.as_u64().map(|c| c.u8()),
+                Some("background") => rgb_attr.cterm_bg = value.as_u64().map(|c| c.u8()),
+                Some("reverse") => rgb_attr.cterm_reverse = value.as_bool().unwrap_or(false),
+                _ => {},
+            }
+        }
         // debug!("hlid: {}", hl_define.id);
         rgb_attrs[hl_define.id.u()] = rgb_attr;
     }
+    if redefined {
+        data.invalidate_front();
+        data.blended_hl.clear();
+        data.layout_dirty = true;
+    }
+    drop(data);
+}
+pub(super) async fn do_hl_group_set(app: &App, _: &impl Nvimapi, events: Vec<uievent::HlGroupSet>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        data.hl_groups.insert(event.name, event.id.u16());
+    }
+    // popupmenu may be using the old ones.
+    if data.popupmenu.is_some() {
+        data.draw_popupmenu();
+    }
     drop(data);
 }
 pub(super) async fn do_grid_resize(this: &App, _: &impl Nvimapi, events: Vec<uievent::GridResize>) {
     let mut data = this.nvimdata.borrow_mut();
-    let size = &events[0];
-    assert_eq!(size.grid, 1);
-    data.nvim_size.w = size.width.u16();
-    data.nvim_size.h = size.height.u16();
-    data.surface = grid::Grid::new(size.height.u(), size.width.u());
+    for size in events {
+        let (w, h) = (size.width.u16(), size.height.u16());
+        if size.grid == MAIN_GRID {
+            data.nvim_size.w = w;
+            data.nvim_size.h = h;
+            data.resize_surface(&this.terminal);
+            let main = data.grid_mut(MAIN_GRID);
+            main.grid_type = GridType::Main;
+            main.shown = true;
+            // overlays drawn by us are placed relative to screen size.
+            data.draw_messages();
+            data.draw_cmdline();
+            data.draw_tabline();
+            data.draw_busy();
+        }
+        data.grid_mut(size.grid).resize(w, h);
+        data.layout_dirty = true;
+    }
     drop(data);
 }
 pub(super) async fn do_grid_clear(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::GridClear>) {
-    assert_eq!(events[0].grid, 1, "I only clear whole screen assuming there is only one grid.");
     let mut data = app.nvimdata.borrow_mut();
-    data.surface.iter_mut().for_each(|v| *v = Default::default());
-    data.apply_hl_id(0, &app.terminal);
-    app.terminal.clear_screen().unwrap();
+    for clear in events {
+        let Some(grid) = data.grids.get_mut(&clear.grid) else {continue};
+        grid.cells.fill(Default::default());
+        // only this grid is cleared, windows over it are kept. So just compose again.
+        data.layout_dirty = true;
+    }
     trace!("grid_clear");
 }
 pub(super) async fn do_grid_cursor_goto(this: &App, _nvim: &impl Nvimapi, events: Vec<uievent::GridCursorGoto>) {
@@ -68,8 +117,7 @@ pub(super) async fn do_grid_cursor_goto(this: &App, _nvim: &impl Nvimapi, events
     for grid_cursor_goto in events {
         let col = grid_cursor_goto.col.u16();
         let row = grid_cursor_goto.row.u16();
-        this.terminal.move_cursor(col, row).unwrap();
-        this.set_cursor(col, row);
+        this.set_cursor(grid_cursor_goto.grid, col, row);
     }
 }
 pub(super) async fn do_grid_line(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::GridLine>) {
@@ -77,57 +125,39 @@ pub(super) async fn do_grid_line(app: &App, _nvim: &impl Nvimapi, events: Vec<ui
     let mut current_hl_id = 1;
     let mut data = app.nvimdata.borrow_mut();
     for line in events {
-        let mut col = line.col_start.u16();
-        let row = line.row.u16();
-        app.terminal.move_cursor(col, row).unwrap();
-        for cell  in line.data.into_iter() {
-            let Value::Array(cell) = cell else {unreachable!()};
-            let mut items = cell.into_iter();
-            let text = items.next().unwrap();
-            let Value::String(text) = text else {unreachable!()};
-            let text = text.into_str().unwrap_or_else(|| String::from("□"));
-            // debug!("text: {text}");
-            if let Some(hl_id) = items.next() {
-                let hl_id = hl_id.as_u64().unwrap().u16();
-                current_hl_id = hl_id;
-                data.apply_hl_id(current_hl_id, &app.terminal);
-            } 
-            let repeat = items.next().map(|v| v.as_i64().unwrap()).unwrap_or(1);
-            // repeat should be signed as it's possible for neovim to send repeat = 0.
-            if repeat > 0 {
-                app.terminal.print(&text).unwrap();
-                let gcell = crate::nvim::data::Cell::new(&text, current_hl_id.u16());
-                data.surface[(row.u(), col.u())] = gcell.clone();
-                col += 1;
-                for _ in 1..repeat {
-                    app.terminal.print(&text).unwrap();
-                    data.surface[(row.u(), col.u())] = gcell.clone();
-                    col += 1;
-                }
-            }
-        }
-        // debug!("g: {grid},r: {row}, c: {col}, t: {buffer}");
+        data.grid_line(line, &mut current_hl_id);
     }
-    app.terminal.move_cursor(data.cursor.pos.col, data.cursor.pos.row).unwrap();
     drop(data);
 }
 pub(super) async fn do_flush(app: &App, _nvim: &impl Nvimapi, _events: Vec<uievent::Flush>) {
     trace!("flush");
+    let mut data = app.nvimdata.borrow_mut();
+    data.settle_scroll_anim();
+    data.place_extmarks(None);
+    data.render(&app.terminal);
+    let animating = data.scroll_anim.is_some();
+    drop(data);
     app.terminal.flush().unwrap();
+    if animating {
+        app.ticker.notify_one();
+    }
 }
 pub(super) async fn do_grid_scroll(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::GridScroll>) {
     log::trace!("grid_scroll");
     let mut data = app.nvimdata.borrow_mut();
     for scroll_event in events {
+        if !(data.config.smooth_scroll && data.start_scroll_anim(&app.terminal, &scroll_event)) {
+            data.scroll_terminal(&app.terminal, &scroll_event);
+        }
         let scroll = scroll_event.rows;
         if scroll > 0 {
             for row in (scroll_event.top + scroll)..scroll_event.bot {
-                handle_scroll_row(app, &mut data, &scroll_event, row);
+                handle_scroll_row(&mut data, &scroll_event, row);
             }
         } else {
             // order of iter has to be reversed or row will overwrite the value from prev loop.
             for row in (scroll_event.top..(scroll_event.bot+scroll)).rev() {
-                handle_scroll_row(app, &mut data, &scroll_event, row);
+                handle_scroll_row(&mut data, &scroll_event, row);
             }
         }
         // debug!("g:{}, t:{}, b:{}, l:{}, r:{}, r:{}, c:{}", scroll_event.grid, scroll_event.top, scroll_event.bot, scroll_event.left, scroll_event.right, scroll_event.rows, scroll_event.cols);
@@ -135,20 +165,80 @@ pub(super) async fn do_grid_scroll(app: &App, _nvim: &impl Nvimapi, events: Vec<
     drop(data);
 }
 
-fn handle_scroll_row(app: &App, data: &mut std::cell::RefMut<'_, super::Data>, scroll_event: &uievent::GridScroll, row: i64) {
+// only the cells are moved, render will print whatever changed.
+fn handle_scroll_row(data: &mut super::Data, scroll_event: &uievent::GridScroll, row: i64) {
     let scroll = scroll_event.rows;
     let row = row.u();
     let Some(new_row) = row.checked_sub_signed(scroll.isize()) else {return;};
-    // let Ok(new_row) = usize::try_from(new_row) else {continue;};
-    app.terminal.move_cursor(scroll_event.left.u16(), new_row.u16()).unwrap();
-    // debug!("move {} to {}.", row, new_row);
-    for col in scroll_event.left..scroll_event.right {
-        let col = col.u();
-        let cell = data.surface[(row.u(), col)].cheap_clone();
-        // let Some(cell) = cell else {continue;};
-        data.apply_hl_id(cell.hl, &app.terminal);
-        app.terminal.print(cell.char_.as_str()).unwrap();
-        data.surface[(new_row, col)] = cell;
+    let Some(grid) = data.grids.get(&scroll_event.grid) else {return;};
+    let cells: Vec<_> = (scroll_event.left..scroll_event.right)
+        .map(|col| grid.cells[(row, col.u())].cheap_clone())
+        .collect();
+    for (col, cell) in (scroll_event.left..scroll_event.right).zip(cells) {
+        data.put_cell(scroll_event.grid, col.u16(), new_row.u16(), cell);
+    }
+}
+
+pub(super) async fn do_win_pos(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::WinPos>) {
+    trace!("win_pos");
+    let mut data = app.nvimdata.borrow_mut();
+    for win_pos in events {
+        let pos = Position::new(win_pos.startcol.u16(), win_pos.startrow.u16());
+        data.set_layout(win_pos.grid, GridType::Normal, pos, 0);
+    }
+}
+pub(super) async fn do_win_float_pos(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::WinFloatPos>) {
+    trace!("win_float_pos");
+    let mut data = app.nvimdata.borrow_mut();
+    for float_pos in events {
+        // actual position is set by place_floats, as anchor grid may move or float may be resized.
+        let float = floats::FloatPos {
+            corner: floats::Corner::from_name(&float_pos.anchor),
+            anchor_grid: float_pos.anchor_grid,
+            row: float_pos.anchor_row,
+            col: float_pos.anchor_col,
+        };
+        data.set_layout(float_pos.grid, GridType::Float, Position::default(), float_pos.zindex);
+        data.grid_mut(float_pos.grid).float = Some(float);
+    }
+}
+pub(super) async fn do_msg_set_pos(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::MsgSetPos>) {
+    trace!("msg_set_pos");
+    let mut data = app.nvimdata.borrow_mut();
+    for msg_pos in events {
+        let pos = Position::new(0, msg_pos.row.u16());
+        data.set_layout(msg_pos.grid, GridType::Message, pos, MESSAGE_ZINDEX);
+    }
+}
+pub(super) async fn do_win_hide(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::WinHide>) {
+    trace!("win_hide");
+    let mut data = app.nvimdata.borrow_mut();
+    for hide in events {
+        if let Some(grid) = data.grids.get_mut(&hide.grid) {
+            grid.shown = false;
+            data.layout_dirty = true;
+        }
+    }
+}
+pub(super) async fn do_win_close(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::WinClose>) {
+    trace!("win_close");
+    let mut data = app.nvimdata.borrow_mut();
+    for close in events {
+        if let Some(grid) = data.grids.get_mut(&close.grid) {
+            grid.shown = false;
+            data.layout_dirty = true;
+        }
+    }
+}
+pub(super) async fn do_grid_destroy(app: &App, _nvim: &impl Nvimapi, events: Vec<uievent::GridDestroy>) {
+    trace!("grid_destroy");
+    let mut data = app.nvimdata.borrow_mut();
+    for destroy in events {
+        data.viewports.remove(&destroy.grid);
+        data.extmarks.remove(&destroy.grid);
+        if data.grids.remove(&destroy.grid).is_some() {
+            data.layout_dirty = true;
+        }
     }
 }
 pub(super) async fn do_mode_change(app: &App, _nvim: &impl Nvimapi, mode_changes: Vec<uievent::ModeChange>) {
@@ -216,3 +306,294 @@ pub(super) async fn do_mode_info_set(app: &App, _nvim: &impl Nvimapi, mode_info_
 // Ignores otherwise. When a ui is focused it broadcasts it.
 // when focus comes back to a tnvim. It broadcast this info and then switches tabpage to it's own
 // and start listening to updates again.
+pub(super) async fn do_popupmenu_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::PopupmenuShow>) {
+    let event = events.into_iter().last().unwrap();
+    let mut data = app.nvimdata.borrow_mut();
+    data.popupmenu = Some(popupmenu::Popupmenu {
+        items: event.items.into_iter().map(popupmenu::PopupmenuItem::from_value).collect(),
+        selected: event.selected,
+        anchor: popupmenu::Anchor { grid: event.grid, row: event.row.u16(), col: event.col.u16() },
+        top: 0,
+    });
+    data.draw_popupmenu();
+    drop(data);
+}
+pub(super) async fn do_popupmenu_select(app: &App, _: &impl Nvimapi, events: Vec<uievent::PopupmenuSelect>) {
+    let mut data = app.nvimdata.borrow_mut();
+    let Some(pum) = &mut data.popupmenu else {return};
+    pum.selected = events.last().unwrap().selected;
+    data.draw_popupmenu();
+    drop(data);
+}
+pub(super) async fn do_popupmenu_hide(app: &App, _: &impl Nvimapi, _events: Vec<uievent::PopupmenuHide>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.popupmenu = None;
+    data.hide_overlay(POPUPMENU_GRID);
+    drop(data);
+}
+pub(super) async fn do_cmdline_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlineShow>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        let level = event.level.u().max(1);
+        let levels = &mut data.cmdline.levels;
+        levels.truncate(level - 1);
+        levels.push(cmdline::CmdlineLevel {
+            content: cmdline::chunks_from_value(event.content),
+            pos: event.pos.u(),
+            firstc: event.firstc,
+            prompt: event.prompt,
+            indent: event.indent.u(),
+            special: None,
+        });
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_pos(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlinePos>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        if let Some(level) = data.cmdline.levels.get_mut(event.level.u().saturating_sub(1)) {
+            level.pos = event.pos.u();
+            level.special = None;
+        }
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_special_char(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlineSpecialChar>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        if let Some(level) = data.cmdline.levels.get_mut(event.level.u().saturating_sub(1)) {
+            level.special = Some((event.c, event.shift));
+        }
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_hide(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlineHide>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        data.cmdline.levels.truncate(event.level.u().saturating_sub(1));
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_block_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlineBlockShow>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        data.cmdline.block = event.lines.into_iter().map(|line| {
+            let Value::Array(line) = line else {unreachable!()};
+            cmdline::chunks_from_value(line)
+        }).collect();
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_block_append(app: &App, _: &impl Nvimapi, events: Vec<uievent::CmdlineBlockAppend>) {
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        // a single line, in spite of the name.
+        data.cmdline.block.push(cmdline::chunks_from_value(event.lines));
+    }
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_cmdline_block_hide(app: &App, _: &impl Nvimapi, _events: Vec<uievent::CmdlineBlockHide>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.cmdline.block.clear();
+    data.draw_cmdline();
+    drop(data);
+}
+pub(super) async fn do_msg_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::MsgShow>) {
+    let mut data = app.nvimdata.borrow_mut();
+    let now = std::time::Instant::now();
+    for event in events {
+        let content = cmdline::chunks_from_value(event.content);
+        data.messages.show(messages::Message::new(event.kind, content, now), event.replace_last);
+    }
+    data.draw_messages();
+    drop(data);
+    // new expiry time.
+    app.ticker.notify_one();
+}
+pub(super) async fn do_msg_clear(app: &App, _: &impl Nvimapi, _events: Vec<uievent::MsgClear>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.messages.clear();
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_msg_showmode(app: &App, _: &impl Nvimapi, events: Vec<uievent::MsgShowmode>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.messages.showmode = cmdline::chunks_from_value(events.into_iter().last().unwrap().content);
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_msg_showcmd(app: &App, _: &impl Nvimapi, events: Vec<uievent::MsgShowcmd>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.messages.showcmd = cmdline::chunks_from_value(events.into_iter().last().unwrap().content);
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_msg_ruler(app: &App, _: &impl Nvimapi, events: Vec<uievent::MsgRuler>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.messages.ruler = cmdline::chunks_from_value(events.into_iter().last().unwrap().content);
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_msg_history_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::MsgHistoryShow>) {
+    let mut data = app.nvimdata.borrow_mut();
+    let entries = events.into_iter().last().unwrap().entries;
+    data.messages.history = Some(messages::History::from_value(entries));
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_msg_history_clear(app: &App, _: &impl Nvimapi, _events: Vec<uievent::MsgHistoryClear>) {
+    let mut data = app.nvimdata.borrow_mut();
+    if let Some(history) = &mut data.messages.history {
+        history.entries.clear();
+    }
+    data.draw_messages();
+    drop(data);
+}
+pub(super) async fn do_tabline_update(app: &App, nvim: &impl Nvimapi, events: Vec<uievent::TablineUpdate>) {
+    let event = events.into_iter().last().unwrap();
+    let mut data = app.nvimdata.borrow_mut();
+    let tabline = tabline::Tabline::new(event);
+    // while attached, nvim's current tabpage is ours.
+    let changed = data.attached && data.my_tab.as_ref().is_none_or(|my| my.0 != tabline.current.0);
+    if changed {
+        data.my_tab = Some(tabline.current.clone());
+    }
+    data.tabline = Some(tabline);
+    let my_tab = data.my_tab.clone();
+    drop(data);
+    if changed {
+        tabline::broadcast_tab(nvim, my_tab.as_ref());
+    }
+    tabline::update_layout(app, nvim);
+}
+pub(super) async fn do_option_set(app: &App, nvim: &impl Nvimapi, events: Vec<uievent::OptionSet>) {
+    for option in events {
+        let name = option.name.clone();
+        app.nvimdata.borrow_mut().options.insert(option.name, option.value);
+        options::apply(app, nvim, &name);
+    }
+}
+pub(super) async fn do_mouse_on(app: &App, _: &impl Nvimapi, _events: Vec<uievent::MouseOn>) {
+    options::set_mouse(app, true);
+}
+pub(super) async fn do_mouse_off(app: &App, _: &impl Nvimapi, _events: Vec<uievent::MouseOff>) {
+    options::set_mouse(app, false);
+}
+pub(super) async fn do_wildmenu_show(app: &App, _: &impl Nvimapi, events: Vec<uievent::WildmenuShow>) {
+    let mut data = app.nvimdata.borrow_mut();
+    let items = events.into_iter().last().unwrap().items;
+    data.wildmenu = Some(wildmenu::Wildmenu::new(items, data.config.wildmenu));
+    data.draw_wildmenu();
+    drop(data);
+}
+pub(super) async fn do_wildmenu_select(app: &App, _: &impl Nvimapi, events: Vec<uievent::WildmenuSelect>) {
+    let mut data = app.nvimdata.borrow_mut();
+    let Some(wildmenu) = &mut data.wildmenu else {return};
+    wildmenu.selected = events.last().unwrap().selected;
+    data.draw_wildmenu();
+    drop(data);
+}
+pub(super) async fn do_wildmenu_hide(app: &App, _: &impl Nvimapi, _events: Vec<uievent::WildmenuHide>) {
+    let mut data = app.nvimdata.borrow_mut();
+    data.wildmenu = None;
+    data.draw_wildmenu();
+    drop(data);
+}
+pub(super) async fn do_win_viewport(app: &App, _: &impl Nvimapi, events: Vec<uievent::WinViewport>) {
+    trace!("win_viewport");
+    let mut data = app.nvimdata.borrow_mut();
+    for event in events {
+        let viewport = viewport::Viewport::new(&event);
+        data.confirm_scroll_anim(event.grid, &viewport);
+        data.viewports.insert(event.grid, viewport);
+        data.place_extmarks(Some(event.grid));
+    }
+    if data.config.scrollbar {
+        data.layout_dirty = true;
+    }
+    drop(data);
+}
+pub(super) async fn do_win_extmark(app: &App, nvim: &impl Nvimapi, events: Vec<uievent::WinExtmark>) {
+    trace!("win_extmark");
+    let mut data = app.nvimdata.borrow_mut();
+    if !data.config.scrollbar_marks { return }
+    let mut unknown_ns = false;
+    for event in events {
+        if let Entry::Vacant(entry) = data.namespaces.entry(event.ns_id) {
+            // asked only once, even if nvim doesn't know its name.
+            entry.insert(String::new());
+            unknown_ns = true;
+        }
+        // placed when topline of the window is known.
+        data.pending_extmarks.push((event.grid, (event.ns_id, event.mark_id), event.row));
+    }
+    drop(data);
+    if unknown_ns {
+        scrollbar::query_namespaces(nvim);
+    }
+}
+pub(super) async fn do_busy_start(app: &App, _: &impl Nvimapi, _events: Vec<uievent::BusyStart>) {
+    trace!("busy_start");
+    let mut data = app.nvimdata.borrow_mut();
+    if data.busy.is_some() { return }
+    data.busy = Some(busy::Busy::new(std::time::Instant::now()));
+    drop(data);
+    app.ticker.notify_one();
+}
+pub(super) async fn do_busy_stop(app: &App, _: &impl Nvimapi, _events: Vec<uievent::BusyStop>) {
+    trace!("busy_stop");
+    let mut data = app.nvimdata.borrow_mut();
+    data.busy = None;
+    data.draw_busy();
+    drop(data);
+}
+pub(super) async fn do_bell(app: &App, _: &impl Nvimapi, _events: Vec<uievent::Bell>) {
+    trace!("bell");
+    let data = app.nvimdata.borrow();
+    if data.should_ring(data.config.bell) {
+        app.terminal.bell().unwrap();
+    }
+    drop(data);
+}
+pub(super) async fn do_visual_bell(app: &App, _: &impl Nvimapi, _events: Vec<uievent::VisualBell>) {
+    trace!("visual_bell");
+    let mut data = app.nvimdata.borrow_mut();
+    if !data.should_ring(data.config.visual_bell) { return }
+    data.flash(&app.terminal, std::time::Instant::now());
+    drop(data);
+    app.ticker.notify_one();
+}
+pub(super) async fn do_chdir(app: &App, _: &impl Nvimapi, events: Vec<uievent::Chdir>) {
+    let Some(chdir) = events.last() else {return};
+    // so that things started from here, and the terminal, are in the same directory as nvim.
+    if let Err(e) = std::env::set_current_dir(&chdir.path) {
+        log::warn!("chdir to {}: {e}", chdir.path);
+        return;
+    }
+    app.terminal.set_cwd(&chdir.path).unwrap();
+}
+pub(super) async fn do_suspend(app: &App, _: &impl Nvimapi, _events: Vec<uievent::Suspend>) {
+    crate::suspend(&app.terminal);
+    let mut data = app.nvimdata.borrow_mut();
+    // shell may have written anything over the screen, all of it is printed again now. nvim may
+    // not send anything till user does something.
+    data.invalidate_front();
+    data.current_hl_id = UNKNOWN_HL;
+    data.scroll_anim = None;
+    data.flash_until = None;
+    // setup showed cursor and captured mouse again.
+    data.cursor_hidden = false;
+    let mouse_off = data.mouse_off;
+    data.render(&app.terminal);
+    drop(data);
+    if mouse_off {
+        app.terminal.disable_mouse_events().unwrap();
+    }
+    app.terminal.flush().unwrap();
+}
@@ -0,0 +1,211 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+use rmpv::Value;
+use suffixes::CastIt as _;
+use super::{Data, cmdline::{Chunk, chunks_from_value}, data::{
+    Cell, Position, CMDLINE_ZINDEX, MESSAGE_ZINDEX,
+    MSG_GRID, MSG_HISTORY_GRID, MSG_PROMPT_GRID, MSG_SHOWMODE_GRID, MSG_STATUS_GRID,
+}};
+
+const TIMEOUT: Duration = Duration::from_secs(4);
+const ERROR_TIMEOUT: Duration = Duration::from_secs(8);
+// older ones are dropped, even if they have not expired.
+const MAX_SHOWN: usize = 10;
+
+/// messages of ext_messages.
+#[derive(Debug, Default)]
+pub struct Messages {
+    // shown as notifications, oldest first.
+    pub shown: VecDeque<Message>,
+    pub showmode: Vec<Chunk>,
+    pub showcmd: Vec<Chunk>,
+    pub ruler: Vec<Chunk>,
+    // :messages, while it's open.
+    pub history: Option<History>,
+}
+#[derive(Debug)]
+pub struct Message {
+    pub kind: String,
+    pub content: Vec<Chunk>,
+    // None for prompts, they stay till nvim clears them.
+    pub expires: Option<Instant>,
+}
+#[derive(Debug, Default)]
+pub struct History {
+    pub entries: Vec<(String, Vec<Chunk>)>,
+    // lines scrolled up from the end.
+    pub scroll: usize,
+}
+
+/// nvim is waiting for the user to answer these.
+fn is_prompt(kind: &str) -> bool {
+    matches!(kind, "return_prompt" | "confirm" | "confirm_sub")
+}
+fn is_error(kind: &str) -> bool {
+    matches!(kind, "emsg" | "echoerr" | "lua_error" | "rpc_error" | "shell_err")
+}
+
+impl Message {
+    pub fn new(kind: String, content: Vec<Chunk>, now: Instant) -> Self {
+        let expires =
+            if is_prompt(&kind) { None }
+            else if is_error(&kind) { Some(now + ERROR_TIMEOUT) }
+            else { Some(now + TIMEOUT) };
+        Self { kind, content, expires }
+    }
+}
+
+impl Messages {
+    pub fn show(&mut self, message: Message, replace_last: bool) {
+        // search count changes with every n, only the last one matters.
+        let replace = replace_last
+            || (message.kind == "search_count" && self.shown.back().is_some_and(|m| m.kind == "search_count"));
+        if replace && let Some(last) = self.shown.back_mut() {
+            *last = message;
+        } else {
+            self.shown.push_back(message);
+        }
+        while self.shown.len() > MAX_SHOWN {
+            let Some(oldest) = self.shown.iter().position(|m| m.expires.is_some()) else {break};
+            self.shown.remove(oldest);
+        }
+    }
+    /// nvim sends msg_clear when the screen is cleared, like after a prompt is answered. Others
+    /// are notifications, they go away with time.
+    pub fn clear(&mut self) {
+        self.shown.retain(|m| !is_prompt(&m.kind));
+    }
+    /// remove expired messages, true if any were removed.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let len = self.shown.len();
+        self.shown.retain(|m| m.expires.is_none_or(|expires| expires > now));
+        return len != self.shown.len();
+    }
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.shown.iter().filter_map(|m| m.expires).min()
+    }
+}
+
+impl History {
+    pub fn from_value(entries: Vec<Value>) -> Self {
+        let entries = entries.into_iter().map(|entry| {
+            let Value::Array(entry) = entry else {unreachable!()};
+            let mut entry = entry.into_iter();
+            let kind = match entry.next() {
+                Some(Value::String(kind)) => kind.into_str().unwrap_or_default(),
+                _ => String::new(),
+            };
+            let content = match entry.next() {
+                Some(Value::Array(content)) => chunks_from_value(content),
+                _ => Vec::new(),
+            };
+            (kind, content)
+        }).collect();
+        Self { entries, scroll: 0 }
+    }
+}
+
+impl Data {
+    pub fn draw_messages(&mut self) {
+        let (rows, cols) = self.surface.size();
+        if rows == 0 || cols == 0 { return }
+        self.draw_notifications(rows, cols);
+        self.draw_prompt(rows, cols);
+        self.draw_msg_status(rows, cols);
+        self.draw_history(rows, cols);
+    }
+    // stacked at top right, newest at bottom.
+    fn draw_notifications(&mut self, rows: usize, cols: usize) {
+        let base_hl = self.hl_group("NormalFloat").unwrap_or(0);
+        let width = (cols * 2 / 3).max(20).min(cols);
+        let mut lines = Vec::new();
+        for message in self.messages.shown.iter().filter(|m| m.expires.is_some()) {
+            for line in self.widths.chunk_lines(&message.content, width.saturating_sub(2).max(1), base_hl) {
+                lines.push(pad(line, base_hl));
+            }
+        }
+        let skipped = lines.len().saturating_sub(rows / 2);
+        let lines: Vec<_> = lines.drain(skipped..).collect();
+        let box_width = lines.iter().map(Vec::len).max().unwrap_or(0).min(cols);
+        let pos = Position::new((cols - box_width).u16(), 0);
+        self.set_overlay(MSG_GRID, pos, box_width, lines, base_hl, MESSAGE_ZINDEX);
+    }
+    // prompts nvim is waiting on, at bottom. Over everything except cmdline.
+    fn draw_prompt(&mut self, rows: usize, cols: usize) {
+        let base_hl = self.hl_group("MsgArea").unwrap_or(0);
+        let mut lines = Vec::new();
+        for message in self.messages.shown.iter().filter(|m| m.expires.is_none()) {
+            lines.extend(self.widths.chunk_lines(&message.content, cols, base_hl));
+        }
+        let skipped = lines.len().saturating_sub(rows);
+        let lines: Vec<_> = lines.drain(skipped..).collect();
+        let pos = Position::new(0, (rows - lines.len()).u16());
+        self.set_overlay(MSG_PROMPT_GRID, pos, cols, lines, base_hl, CMDLINE_ZINDEX - 1);
+    }
+    // showmode at left of last row. showcmd and ruler at right.
+    fn draw_msg_status(&mut self, rows: usize, cols: usize) {
+        let base_hl = self.hl_group("MsgArea").unwrap_or(0);
+        let last_row = (rows - 1).u16();
+        let showmode: Vec<_> = self.widths.chunk_lines(&self.messages.showmode, cols, base_hl)
+            .into_iter().take(1).filter(|line| !line.is_empty()).collect();
+        let width = showmode.first().map(Vec::len).unwrap_or(0);
+        self.set_overlay(MSG_SHOWMODE_GRID, Position::new(0, last_row), width, showmode, base_hl, MESSAGE_ZINDEX - 1);
+
+        let mut status = self.messages.showcmd.clone();
+        if !status.is_empty() && !self.messages.ruler.is_empty() {
+            status.push((0, String::from("  ")));
+        }
+        status.extend(self.messages.ruler.iter().cloned());
+        let status: Vec<_> = self.widths.chunk_lines(&status, cols, base_hl)
+            .into_iter().take(1).filter(|line| !line.is_empty()).map(|line| pad(line, base_hl)).collect();
+        let width = status.first().map(Vec::len).unwrap_or(0).min(cols);
+        let pos = Position::new((cols - width).u16(), last_row);
+        self.set_overlay(MSG_STATUS_GRID, pos, width, status, base_hl, MESSAGE_ZINDEX - 1);
+    }
+    // bottom half of screen, with a title line.
+    fn draw_history(&mut self, rows: usize, cols: usize) {
+        let Some(history) = &self.messages.history else {
+            self.hide_overlay(MSG_HISTORY_GRID);
+            return;
+        };
+        let base_hl = self.hl_group("NormalFloat").unwrap_or(0);
+        let title_hl = self.hl_group("Title").unwrap_or(base_hl);
+        let mut lines = Vec::new();
+        for (_, content) in &history.entries {
+            lines.extend(self.widths.chunk_lines(content, cols, base_hl));
+        }
+        let height = lines.len().min((rows / 2).max(1));
+        let max_scroll = lines.len() - height;
+        let scroll = history.scroll.min(max_scroll);
+        let end = lines.len() - scroll;
+        let mut shown = vec![self.widths.text_cells(" Messages  (j/k to scroll, q to close)", title_hl)];
+        shown.extend(lines.drain(end - height..end));
+        let pos = Position::new(0, (rows - shown.len()).u16());
+        if let Some(history) = &mut self.messages.history { history.scroll = scroll; }
+        self.set_overlay(MSG_HISTORY_GRID, pos, cols, shown, base_hl, CMDLINE_ZINDEX - 2);
+    }
+    /// remove expired messages. true if something changed on screen.
+    pub fn expire_messages(&mut self, now: Instant) -> bool {
+        if !self.messages.expire(now) { return false }
+        self.draw_messages();
+        return true;
+    }
+    /// scroll :messages, positive is towards older ones.
+    pub fn scroll_history(&mut self, lines: isize) {
+        let Some(history) = &mut self.messages.history else {return};
+        history.scroll = history.scroll.saturating_add_signed(lines);
+        self.draw_messages();
+    }
+    pub fn close_history(&mut self) {
+        self.messages.history = None;
+        self.draw_messages();
+    }
+}
+
+// a space on both sides.
+fn pad(line: Vec<Cell>, hl: u16) -> Vec<Cell> {
+    let mut padded = Vec::with_capacity(line.len() + 2);
+    padded.push(Cell::new(" ", hl));
+    padded.extend(line);
+    padded.push(Cell::new(" ", hl));
+    return padded;
+}
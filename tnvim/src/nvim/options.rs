@@ -0,0 +1,65 @@
+// Options of nvim, from option_set. All of them are kept as they came, some change how tnvim draws.
+use nvimapi::Nvimapi;
+use rmpv::Value;
+use crate::app::App;
+use super::{Data, data::UNKNOWN_HL, tabline, text};
+
+impl Data {
+    /// value of an option, as nvim sent it in option_set.
+    pub fn option(&self, name: &str) -> Option<&Value> {
+        self.options.get(name)
+    }
+    /// on till nvim says otherwise, as it's what tnvim did before it knew about it.
+    pub fn termguicolors(&self) -> bool {
+        self.option("termguicolors").and_then(Value::as_bool).unwrap_or(true)
+    }
+}
+
+/// option_set came for name, its value is in the table already.
+pub fn apply(app: &App, nvim: &impl Nvimapi, name: &str) {
+    match name {
+        "showtabline" => {
+            let mut data = app.nvimdata.borrow_mut();
+            data.showtabline = data.option(name).and_then(Value::as_i64).unwrap_or(1);
+            drop(data);
+            tabline::update_layout(app, nvim);
+        },
+        "termguicolors" => {
+            let mut data = app.nvimdata.borrow_mut();
+            // everything is printed again, with colours of the other kind.
+            data.invalidate_front();
+            data.current_hl_id = UNKNOWN_HL;
+            drop(data);
+        },
+        "ambiwidth" | "emoji" => {
+            let mut data = app.nvimdata.borrow_mut();
+            data.widths = text::Widths {
+                ambiwidth_double: data.option("ambiwidth").and_then(Value::as_str) == Some("double"),
+                emoji_wide: data.option("emoji").and_then(Value::as_bool).unwrap_or(true),
+            };
+            // what tnvim draws is laid out again with new widths.
+            data.draw_messages();
+            data.draw_cmdline();
+            data.draw_tabline();
+            data.draw_popupmenu();
+            drop(data);
+        },
+        // mouse is not sent here, mouse_on and mouse_off tell when to capture it.
+        // fonts and space between lines are up to the terminal.
+        "guifont" | "guifontwide" | "linespace" => {},
+        _ => {},
+    }
+}
+
+/// Capture mouse of terminal or give it back, so that terminal can select text with it.
+pub fn set_mouse(app: &App, on: bool) {
+    let mut data = app.nvimdata.borrow_mut();
+    if data.mouse_off != on { return }
+    data.mouse_off = !on;
+    drop(data);
+    if on {
+        app.terminal.enable_mouse_events().unwrap();
+    } else {
+        app.terminal.disable_mouse_events().unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use rmpv::Value;
+use suffixes::CastIt as _;
+use super::{Data, data::{Cell, GridType, Position, CMDLINE_ZINDEX, POPUPMENU_GRID, POPUPMENU_ZINDEX}, text};
+
+// more than this are scrolled.
+const MAX_HEIGHT: usize = 15;
+// kind, menu and info are cut to this.
+const MAX_EXTRA_WIDTH: usize = 40;
+
+#[derive(Debug, Default)]
+pub struct Popupmenu {
+    pub items: Vec<PopupmenuItem>,
+    // -1 when nothing is selected.
+    pub selected: i64,
+    // where the completed word starts.
+    pub anchor: Anchor,
+    // first item which is shown.
+    pub top: usize,
+}
+#[derive(Debug, Default)]
+pub struct PopupmenuItem {
+    pub word: String,
+    pub kind: String,
+    pub menu: String,
+    pub info: String,
+}
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Anchor {
+    pub grid: i64,
+    pub row: u16,
+    pub col: u16,
+}
+
+impl PopupmenuItem {
+    pub fn from_value(item: Value) -> Self {
+        let Value::Array(item) = item else {unreachable!()};
+        let mut fields = item.into_iter().map(|v| match v {
+            Value::String(s) => s.into_str().unwrap_or_default(),
+            _ => String::new(),
+        });
+        let mut next = || fields.next().unwrap_or_default();
+        Self { word: next(), kind: next(), menu: next(), info: next() }
+    }
+}
+
+// hl ids of the groups popupmenu is drawn with.
+struct PmenuHl {
+    normal: u16,
+    sel: u16,
+    kind: u16,
+    kind_sel: u16,
+    extra: u16,
+    extra_sel: u16,
+    sbar: u16,
+    thumb: u16,
+}
+
+impl Data {
+    /// hl id nvim uses for a builtin highlight group, from hl_group_set.
+    pub fn hl_group(&self, name: &str) -> Option<u16> {
+        self.hl_groups.get(name).copied()
+    }
+    fn pmenu_hl(&self) -> PmenuHl {
+        let normal = self.hl_group("Pmenu").unwrap_or(0);
+        let sel = self.hl_group("PmenuSel").unwrap_or(normal);
+        PmenuHl {
+            normal,
+            sel,
+            kind: self.hl_group("PmenuKind").unwrap_or(normal),
+            kind_sel: self.hl_group("PmenuKindSel").unwrap_or(sel),
+            extra: self.hl_group("PmenuExtra").unwrap_or(normal),
+            extra_sel: self.hl_group("PmenuExtraSel").unwrap_or(sel),
+            sbar: self.hl_group("PmenuSbar").unwrap_or(normal),
+            thumb: self.hl_group("PmenuThumb").unwrap_or(sel),
+        }
+    }
+    /// Build the popupmenu overlay grid from self.popupmenu.
+    pub fn draw_popupmenu(&mut self) {
+        let hl = self.pmenu_hl();
+        let (rows, cols) = self.surface.size();
+        let Some(pum) = &self.popupmenu else {return};
+        if pum.items.is_empty() || rows == 0 || cols == 0 {
+            self.hide_overlay(POPUPMENU_GRID);
+            return;
+        }
+        let anchor = pum.anchor;
+        // grid -1 is cmdline, col is from start of its content.
+        let cmdline_anchor = || {
+            let start = self.cmdline.content_start?;
+            let cursor = self.cmdline.cursor?;
+            Some(Position::new((start.col + anchor.col).min(cols.u16() - 1), cursor.row))
+        };
+        let anchor_pos =
+            if anchor.grid == -1 { cmdline_anchor() }
+            else { self.to_screen(anchor.grid, anchor.col, anchor.row) };
+        let anchor_pos = anchor_pos.unwrap_or(Position::new(anchor.col.min(cols.u16() - 1), rows.u16() - 1));
+        let (arow, acol) = (anchor_pos.row.u(), anchor_pos.col.u());
+
+        // below the anchor if it fits, else wherever there is more space.
+        let wanted = pum.items.len().min(MAX_HEIGHT);
+        let below = rows - arow - 1;
+        let above = arow;
+        let (top_row, height) =
+            if wanted <= below || below >= above { (arow + 1, wanted.min(below)) }
+            else { let height = wanted.min(above); (arow - height, height) };
+        if height == 0 {
+            self.hide_overlay(POPUPMENU_GRID);
+            return;
+        }
+
+        let column_width = |f: fn(&PopupmenuItem) -> &str, max: usize| {
+            pum.items.iter().map(|item| self.widths.text_width(f(item))).max().unwrap_or(0).min(max)
+        };
+        let word_w = column_width(|i| &i.word, cols);
+        let extras = [
+            column_width(|i| &i.kind, MAX_EXTRA_WIDTH),
+            column_width(|i| &i.menu, MAX_EXTRA_WIDTH),
+            column_width(|i| &i.info, MAX_EXTRA_WIDTH),
+        ];
+        let scrollbar = pum.items.len() > height;
+        // a space on both sides, and between columns.
+        let mut width = 1 + word_w + 1 + extras.iter().filter(|&&w| w > 0).map(|w| w + 1).sum::<usize>();
+        if scrollbar { width += 1; }
+        let width = width.min(cols);
+        // a space before the word, so it starts at anchor col.
+        let left = acol.saturating_sub(1).min(cols - width);
+
+        // keep selected in view.
+        let mut top = pum.top.min(pum.items.len() - height);
+        if pum.selected >= 0 {
+            let selected = pum.selected.u();
+            if selected < top { top = selected; }
+            if selected >= top + height { top = selected + 1 - height; }
+        }
+
+        let text_end = if scrollbar { width - 1 } else { width };
+        let mut cells = grid::Grid::init(height, width, Cell::default());
+        for row in 0..height {
+            let index = top + row;
+            let item = &pum.items[index];
+            let selected = pum.selected >= 0 && pum.selected.u() == index;
+            let (normal, kind, extra) =
+                if selected { (hl.sel, hl.kind_sel, hl.extra_sel) }
+                else { (hl.normal, hl.kind, hl.extra) };
+            text::fill(&mut cells, row, 0, text_end, normal);
+            let mut col = 1;
+            self.widths.put_text(&mut cells, row, col, text_end, &item.word, normal);
+            col += word_w + 1;
+            for (w, (text, hl)) in extras.iter().zip([(&item.kind, kind), (&item.menu, extra), (&item.info, extra)]) {
+                if *w == 0 { continue }
+                self.widths.put_text(&mut cells, row, col, (col + w).min(text_end), text, hl);
+                col += w + 1;
+            }
+        }
+        if scrollbar {
+            // thumb size and position, proportional to the part shown.
+            let total = pum.items.len();
+            let thumb_h = (height * height).div_ceil(total).max(1);
+            let thumb_top = (top * height) / total;
+            for row in 0..height {
+                let in_thumb = row >= thumb_top && row < thumb_top + thumb_h;
+                cells[(row, width - 1)] = Cell::new(" ", if in_thumb { hl.thumb } else { hl.sbar });
+            }
+        }
+
+        if let Some(pum) = &mut self.popupmenu { pum.top = top; }
+        let g = self.grid_mut(POPUPMENU_GRID);
+        g.cells = cells;
+        g.size = super::data::Size { w: width.u16(), h: height.u16() };
+        // completion of cmdline has to be over it.
+        let zindex = if anchor.grid == -1 { CMDLINE_ZINDEX + 1 } else { POPUPMENU_ZINDEX };
+        self.set_layout(POPUPMENU_GRID, GridType::Overlay, Position::new(left.u16(), top_row.u16()), zindex);
+    }
+    /// index of popupmenu item shown at this position of screen.
+    pub fn popupmenu_item_at(&self, col: u16, row: u16) -> Option<usize> {
+        let pum = self.popupmenu.as_ref()?;
+        if self.owner_at(col, row)? != POPUPMENU_GRID { return None }
+        let grid = self.grids.get(&POPUPMENU_GRID)?;
+        let index = pum.top + (row - grid.pos.row).u();
+        return (index < pum.items.len()).then_some(index);
+    }
+}
@@ -0,0 +1,162 @@
+use cheapclone::CheapClone as _;
+use nvimapi::uievent;
+use suffixes::CastIt as _;
+use crate::terminal::Terminal;
+use super::{Data, data::Cell};
+
+impl Data {
+    /// Print the cells of surface which differ from front. After this front is same as surface.
+    /// Consecutive changed cells with same hl are printed together.
+    pub fn render(&mut self, term: &Terminal) {
+        // it's all printed after the flash.
+        if self.flash_until.is_some() { return }
+        if self.layout_dirty {
+            self.compose();
+        }
+        if self.front.size() != self.surface.size() {
+            self.invalidate_front();
+        }
+        self.advance_scroll_anim(term);
+        // while a scroll is animated, rows of its region show surface from some rows away.
+        let anim = self.scroll_anim.take();
+        let src_row = |row: usize, col: usize| match &anim {
+            Some(anim) => anim.src_row(row, col),
+            None => Some(row),
+        };
+        let (rows, cols) = self.surface.size();
+        let mut run = String::new();
+        // url of the link currently open on terminal. Runs with same url share it.
+        let mut link = String::new();
+        for row in 0..rows {
+            let mut col = 0;
+            // where the terminal cursor is after last print on this row.
+            let mut term_col = None;
+            self.fix_wide_cells(row);
+            while col < cols {
+                let Some(srow) = src_row(row, col) else {
+                    col += 1;
+                    continue;
+                };
+                if self.surface[(srow, col)] == self.front[(row, col)] {
+                    col += 1;
+                    continue;
+                }
+                // right half of a wide char changed alone, like its hl. Wide char is printed again
+                // from its left half, so that it lines up.
+                if self.surface[(srow, col)].is_continuation() && col > 0
+                    && src_row(row, col - 1) == Some(srow) && self.surface[(srow, col - 1)].width == 2
+                {
+                    col -= 1;
+                }
+                let start = col;
+                let hl = self.surface[(srow, start)].hl;
+                run.clear();
+                // terminal may not agree with nvim on width of a wide char. So cursor is placed
+                // explicitly after them.
+                let mut after_wide = false;
+                while col < cols {
+                    if src_row(row, col) != Some(srow) { break }
+                    let cell = &self.surface[(srow, col)];
+                    if cell.hl != hl || (col != start && *cell == self.front[(row, col)]) { break }
+                    run.push_str(cell.char_.as_str());
+                    self.front[(row, col)] = cell.cheap_clone();
+                    col += 1;
+                    if cell.width == 2 {
+                        // right half goes with it, fix_wide_cells made sure it's there.
+                        self.front[(row, col)] = self.surface[(srow, col)].cheap_clone();
+                        col += 1;
+                        after_wide = true;
+                        break;
+                    }
+                }
+                // only right half of a wide char, which is already shown.
+                if run.is_empty() { continue; }
+                if term_col != Some(start) {
+                    close_link(term, &mut link);
+                    term.move_cursor(start.u16(), row.u16()).unwrap();
+                }
+                let url = self.hl_url(hl);
+                if url != link {
+                    close_link(term, &mut link);
+                    if !url.is_empty() {
+                        term.open_link(url).unwrap();
+                        link.push_str(url);
+                    }
+                }
+                self.apply_hl_id(hl, term);
+                term.print(&run).unwrap();
+                term_col = if after_wide { None } else { Some(col) };
+            }
+            close_link(term, &mut link);
+        }
+        self.scroll_anim = anim;
+        self.end_scroll_anim();
+        let cursor = self.cursor_screen_pos();
+        term.move_cursor(cursor.col, cursor.row).unwrap();
+        // nvim doesn't want the cursor seen while it's busy.
+        let hide = self.busy.is_some();
+        if hide != self.cursor_hidden {
+            if hide { term.hide_cursor().unwrap(); } else { term.show_cursor().unwrap(); }
+            self.cursor_hidden = hide;
+        }
+    }
+
+    /// Scroll the region on terminal itself, so that only the rows exposed by grid_scroll are
+    /// printed on render. Without left and right margins, only full width regions can be scrolled.
+    pub fn scroll_terminal(&mut self, term: &Terminal, scroll: &uievent::GridScroll) {
+        let Some(grid) = self.grids.get(&scroll.grid) else {return};
+        if !grid.shown || self.layout_dirty || self.front.size() != self.surface.size() { return }
+        let origin = self.origin(scroll.grid, grid);
+        let top = origin.row.u() + scroll.top.u();
+        let bot = origin.row.u() + scroll.bot.u();
+        let left = origin.col.u() + scroll.left.u();
+        let right = origin.col.u() + scroll.right.u();
+        self.scroll_region(term, top, bot, left, right, scroll.rows);
+    }
+    /// Scroll rows top..bot of terminal, and front with it. Positive rows move content up.
+    pub fn scroll_region(&mut self, term: &Terminal, top: usize, bot: usize, left: usize, right: usize, rows: i64) {
+        let (surface_rows, cols) = self.surface.size();
+        let count = rows.unsigned_abs().u();
+        // nothing would be left to reuse.
+        if count == 0 || bot > surface_rows || right > cols || top + count >= bot { return }
+        let full_width = left == 0 && right == cols;
+        if !full_width && !term.caps.lr_margins { return }
+        if !full_width {
+            term.set_lr_margins(left.u16(), right.u16()).unwrap();
+        }
+        term.set_scroll_region(top.u16(), bot.u16()).unwrap();
+        if rows > 0 {
+            term.scroll_up(count.u16()).unwrap();
+        } else {
+            term.scroll_down(count.u16()).unwrap();
+        }
+        term.reset_scroll_region().unwrap();
+        if !full_width {
+            term.reset_lr_margins().unwrap();
+        }
+        // same thing for front.
+        let src_row = |row: usize| {
+            if rows > 0 { Some(row + count).filter(|&r| r < bot) }
+            else { row.checked_sub(count).filter(|&r| r >= top) }
+        };
+        let dst_rows: Vec<usize> =
+            if rows > 0 { (top..bot).collect() }
+            else { (top..bot).rev().collect() };
+        for row in dst_rows {
+            let src = src_row(row);
+            for col in left..right {
+                self.front[(row, col)] = match src {
+                    Some(src) => self.front[(src, col)].cheap_clone(),
+                    // terminal fills them with current bg. Just print them again.
+                    None => Cell::unknown(),
+                };
+            }
+        }
+    }
+}
+
+fn close_link(term: &Terminal, link: &mut String) {
+    if link.is_empty() { return }
+    term.close_link().unwrap();
+    link.clear();
+}
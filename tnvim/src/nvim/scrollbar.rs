@@ -0,0 +1,115 @@
+// Scrollbar drawn over last column of windows, from win_viewport. With marks of extmarks nvim
+// tells about in win_extmark, like diagnostics or search matches of plugins.
+use nvimapi::{Nvimapi, NvimapiNr as _};
+use rmpv::Value;
+use rustc_hash::FxHashMap;
+use suffixes::CastIt as _;
+use crate::app::App;
+use super::{Data, data::{Cell, GridId, GridType, SCROLLBAR_GRID}};
+
+/// extmarks seen in a window, by (ns_id, mark_id), with the buffer line they are at.
+/// nvim only tells about marks while they are visible, and not when they are deleted. So these
+/// are where the marks were when last seen.
+pub type Marks = FxHashMap<(i64, i64), i64>;
+
+// what a mark is about, from name of its namespace. Later ones are drawn over earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MarkKind {
+    Other,
+    Search,
+    Diagnostic,
+}
+
+impl MarkKind {
+    fn from_namespace(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if name.contains("diagnostic") || name.contains("lsp") { Self::Diagnostic }
+        else if name.contains("search") { Self::Search }
+        else { Self::Other }
+    }
+    fn hl_group(self) -> &'static str {
+        match self {
+            Self::Diagnostic => "ErrorMsg",
+            Self::Search => "Search",
+            Self::Other => "WarningMsg",
+        }
+    }
+}
+
+impl Data {
+    /// Scrollbar over last column of windows, where the window is not covered. Called by compose.
+    pub fn draw_scrollbars(&mut self) {
+        let sbar = self.hl_group("PmenuSbar").unwrap_or(0);
+        let thumb = self.hl_group("PmenuThumb").unwrap_or(sbar);
+        let (rows, cols) = self.surface.size();
+        for (id, viewport) in &self.viewports {
+            let Some(grid) = self.grids.get(id) else {continue};
+            if !grid.shown || grid.grid_type != GridType::Normal || grid.size.w == 0 { continue }
+            let height = grid.size.h.u();
+            let Some((thumb_top, thumb_h)) = viewport.thumb(height) else {continue};
+            let marks = if self.config.scrollbar_marks { self.mark_rows(*id, height) } else { Vec::new() };
+            let origin = self.origin(*id, grid);
+            let col = origin.col.u() + grid.size.w.u() - 1;
+            for row in 0..height {
+                let srow = origin.row.u() + row;
+                if srow >= rows || col >= cols || self.owner[(srow, col)] != *id { continue }
+                let in_thumb = row >= thumb_top && row < thumb_top + thumb_h;
+                let bar_hl = if in_thumb { thumb } else { sbar };
+                let cell = match marks.get(row).copied().flatten() {
+                    Some(kind) => Cell::new("─", self.hl_group(kind.hl_group()).unwrap_or(bar_hl)),
+                    None => Cell::new(" ", bar_hl),
+                };
+                self.surface[(srow, col)] = cell;
+                // so put_cell leaves it alone.
+                self.owner[(srow, col)] = SCROLLBAR_GRID;
+            }
+        }
+    }
+    // kind of mark to show at each row of scrollbar of grid.
+    fn mark_rows(&self, grid: GridId, height: usize) -> Vec<Option<MarkKind>> {
+        let mut rows = vec![None; height];
+        let (Some(marks), Some(viewport)) = (self.extmarks.get(&grid), self.viewports.get(&grid)) else { return rows };
+        let total = viewport.line_count.max(1).u();
+        for ((ns_id, _), line) in marks {
+            let kind = MarkKind::from_namespace(self.namespaces.get(ns_id).map(String::as_str).unwrap_or(""));
+            let row = ((line.max(&0).u() * height) / total).min(height - 1);
+            rows[row] = rows[row].max(Some(kind));
+        }
+        return rows;
+    }
+    /// extmarks of win_extmark, which came before win_viewport of their window, are placed with
+    /// its topline. grid None for all of them, with the topline already known.
+    pub fn place_extmarks(&mut self, grid: Option<GridId>) {
+        let pending = core::mem::take(&mut self.pending_extmarks);
+        for (mark_grid, key, row) in pending {
+            if grid.is_some_and(|grid| grid != mark_grid) {
+                self.pending_extmarks.push((mark_grid, key, row));
+                continue;
+            }
+            // wrapped lines and folds are not accounted for, it's close enough for scrollbar.
+            let topline = self.viewports.get(&mark_grid).map(|v| v.topline).unwrap_or(0);
+            self.extmarks.entry(mark_grid).or_default().insert(key, topline + row);
+            self.layout_dirty |= self.config.scrollbar;
+        }
+    }
+}
+
+/// Ask nvim for names of namespaces, they come back as tnvim.namespaces.
+pub fn query_namespaces(nvim: &impl Nvimapi) {
+    let code = "vim.rpcnotify(0, 'tnvim.namespaces', vim.api.nvim_get_namespaces())";
+    super::check(nvim.nr().exec_lua(code, [(); 0]), "query_namespaces");
+}
+
+/// tnvim.namespaces, a map of name to id.
+pub fn on_namespaces(app: &App, args: Value) {
+    let Value::Array(args) = args else {return};
+    let Some(Value::Map(namespaces)) = args.into_iter().next() else {return};
+    let mut data = app.nvimdata.borrow_mut();
+    for (name, id) in namespaces {
+        let (Some(name), Some(id)) = (name.as_str(), id.as_i64()) else {continue};
+        data.namespaces.insert(id, name.to_owned());
+    }
+    data.layout_dirty = true;
+    drop(data);
+    app.redraw();
+}
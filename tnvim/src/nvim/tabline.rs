@@ -0,0 +1,183 @@
+use core::ops::Range;
+use nvimapi::{Nvimapi, NvimapiNr as _, uievent::{Buffer, Tabpage}};
+use rmpv::Value;
+use suffixes::CastIt as _;
+use crate::app::App;
+use super::{Data, data::{Cell, Position, TABLINE_GRID, TABLINE_ZINDEX}};
+
+#[derive(Debug)]
+pub struct Tabline {
+    pub current: Tabpage,
+    pub tabs: Vec<(Tabpage, String)>,
+    pub current_buffer: Buffer,
+    pub buffers: Vec<(Buffer, String)>,
+    // what is under which columns, for clicks.
+    pub targets: Vec<(Range<u16>, Target)>,
+}
+#[derive(Debug, Clone)]
+pub enum Target {
+    Tab(Tabpage),
+    Buffer(Buffer),
+}
+
+impl Tabline {
+    pub fn new(event: nvimapi::uievent::TablineUpdate) -> Self {
+        let entries = |list: Vec<Value>, key: &str| -> Vec<(Value, String)> {
+            list.into_iter().map(|entry| {
+                let id = super::value_get(&entry, key).cloned().unwrap_or(Value::Nil);
+                let name = super::value_get(&entry, "name").and_then(|v| v.as_str()).unwrap_or("").to_owned();
+                (id, name)
+            }).collect()
+        };
+        Self {
+            current: event.current,
+            tabs: entries(event.tabs, "tab").into_iter().map(|(t, n)| (Tabpage(t), n)).collect(),
+            current_buffer: event.current_buffer,
+            buffers: entries(event.buffers, "buffer").into_iter().map(|(b, n)| (Buffer(b), n)).collect(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+// last part of path, as full paths don't fit.
+fn short_name(name: &str) -> &str {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    if name.is_empty() { "[No Name]" } else { name }
+}
+
+impl Data {
+    /// rows taken by tabline, depending on showtabline.
+    pub fn tabline_rows(&self) -> u16 {
+        let tabs = self.tabline.as_ref().map(|t| t.tabs.len()).unwrap_or(0);
+        match self.showtabline {
+            0 => 0,
+            1 if tabs < 2 => 0,
+            _ => 1,
+        }
+    }
+    /// Draw the tabline overlay in the rows above nvim's grids.
+    /// ● marks tab of this tnvim, ○ tabs of other tnvims.
+    pub fn draw_tabline(&mut self) {
+        let cols = self.surface.cols();
+        let Some(tabline) = &self.tabline else {return};
+        if self.top_rows == 0 || cols == 0 {
+            self.hide_overlay(TABLINE_GRID);
+            return;
+        }
+        let hl = self.hl_group("TabLine").unwrap_or(0);
+        let sel_hl = self.hl_group("TabLineSel").unwrap_or(hl);
+        let fill_hl = self.hl_group("TabLineFill").unwrap_or(hl);
+        let mut line: Vec<Cell> = Vec::new();
+        let mut targets = Vec::new();
+        let mut push = |line: &mut Vec<Cell>, text: &str, hl: u16, target: Option<Target>| {
+            let start = line.len().u16();
+            line.extend(self.widths.text_cells(text, hl));
+            if let Some(target) = target {
+                targets.push((start..line.len().u16(), target));
+            }
+        };
+        for (i, (tab, name)) in tabline.tabs.iter().enumerate() {
+            let mark =
+                if self.my_tab.as_ref().is_some_and(|my| my.0 == tab.0) { "●" }
+                else if self.other_tabs.values().any(|other| other.0 == tab.0) { "○" }
+                else { " " };
+            let hl = if tab.0 == tabline.current.0 { sel_hl } else { hl };
+            let label = format!(" {}{mark}{} ", i + 1, short_name(name));
+            push(&mut line, &label, hl, Some(Target::Tab(tab.clone())));
+        }
+        if !tabline.buffers.is_empty() {
+            push(&mut line, "│", fill_hl, None);
+        }
+        for (buffer, name) in &tabline.buffers {
+            let hl = if buffer.0 == tabline.current_buffer.0 { sel_hl } else { hl };
+            let label = format!(" {} ", short_name(name));
+            push(&mut line, &label, hl, Some(Target::Buffer(buffer.clone())));
+        }
+        if let Some(tabline) = &mut self.tabline { tabline.targets = targets; }
+        self.set_overlay(TABLINE_GRID, Position::new(0, 0), cols, vec![line], fill_hl, TABLINE_ZINDEX);
+    }
+    /// what is clicked on tabline at col.
+    pub fn tabline_target(&self, col: u16) -> Option<Target> {
+        let tabline = self.tabline.as_ref()?;
+        tabline.targets.iter().find(|(range, _)| range.contains(&col)).map(|(_, target)| target.clone())
+    }
+    /// size to ask nvim for. Rows taken by tnvim, like tabline, are left out.
+    pub fn nvim_request_size(&self) -> (u16, u16) {
+        (self.ui_size.w, self.ui_size.h.saturating_sub(self.top_rows).max(1))
+    }
+}
+
+/// Tell other tnvims which tabpage is ours. None when we don't have one anymore.
+pub fn broadcast_tab(nvim: &impl Nvimapi, tab: Option<&Tabpage>) {
+    super::check(nvim.nr().call_function("rpcnotify", (0, "tnvim.tab", std::process::id(), tab)), "broadcast_tab");
+}
+/// Ask other tnvims to broadcast their tabpages.
+pub fn query_tabs(nvim: &impl Nvimapi) {
+    super::check(nvim.nr().call_function("rpcnotify", (0, "tnvim.tab_query", std::process::id())), "query_tabs");
+}
+
+/// Have the server broadcast None for our tab, once our channel is closed. tnvim may be killed
+/// without a chance to do it itself. Channels are checked when a ui comes or goes, as tnvims attach
+/// and detach on focus change.
+pub fn watch_leave(nvim: &impl Nvimapi) {
+    let code = "local pid = ... \
+        local chan = vim.api.nvim_get_api_info()[1] \
+        _G.tnvim_pids = _G.tnvim_pids or {} \
+        _G.tnvim_pids[chan] = pid \
+        local group = vim.api.nvim_create_augroup('tnvim_tabs', {}) \
+        vim.api.nvim_create_autocmd({'UIEnter', 'UILeave'}, {group = group, callback = function() \
+            vim.schedule(function() \
+                for c, p in pairs(_G.tnvim_pids) do \
+                    if vim.tbl_isempty(vim.api.nvim_get_chan_info(c)) then \
+                        _G.tnvim_pids[c] = nil \
+                        vim.rpcnotify(0, 'tnvim.tab', p) \
+                    end \
+                end \
+            end) \
+        end})";
+    super::check(nvim.nr().exec_lua(code, (std::process::id(),)), "watch_leave");
+}
+
+/// tnvim.tab and tnvim.tab_query from other tnvims.
+pub fn on_notification(app: &App, nvim: &impl Nvimapi, name: &str, args: Value) {
+    let Value::Array(args) = args else {return};
+    let mut args = args.into_iter();
+    let Some(pid) = args.next().and_then(|v| v.as_u64()) else {return};
+    // rpcnotify to 0 comes back to us too.
+    if pid == u64::from(std::process::id()) { return }
+    match name {
+        "tnvim.tab" => {
+            let mut data = app.nvimdata.borrow_mut();
+            match args.next() {
+                Some(Value::Nil) | None => { data.other_tabs.remove(&pid); },
+                Some(tab) => { data.other_tabs.insert(pid, Tabpage(tab)); },
+            }
+            data.draw_tabline();
+            drop(data);
+            app.redraw();
+        },
+        "tnvim.tab_query" => {
+            let my_tab = app.nvimdata.borrow().my_tab.clone();
+            if my_tab.is_some() {
+                broadcast_tab(nvim, my_tab.as_ref());
+            }
+        },
+        _ => {},
+    }
+}
+
+/// tabs or showtabline changed. Takes or gives back the row of tabline.
+pub fn update_layout(app: &App, nvim: &impl Nvimapi) {
+    let mut data = app.nvimdata.borrow_mut();
+    let rows = data.tabline_rows();
+    if rows != data.top_rows {
+        data.top_rows = rows;
+        data.resize_surface(&app.terminal);
+        if data.attached {
+            let (w, h) = data.nvim_request_size();
+            super::check(nvim.nr().ui_try_resize(w.into(), h.into()), "ui_try_resize");
+        }
+    }
+    data.draw_tabline();
+    drop(data);
+}
@@ -0,0 +1,158 @@
+// Laying out text that tnvim draws itself (popupmenu, cmdline ...) into cells, the way nvim would
+// have sent them in grid_line.
+use super::data::Cell;
+
+/// how wide some chars are, from 'ambiwidth' and 'emoji' options of nvim.
+#[derive(Debug, Clone, Copy)]
+pub struct Widths {
+    // 'ambiwidth' is double.
+    pub ambiwidth_double: bool,
+    // 'emoji', on by default.
+    pub emoji_wide: bool,
+}
+impl Default for Widths {
+    fn default() -> Self {
+        Self { ambiwidth_double: false, emoji_wide: true }
+    }
+}
+
+impl Widths {
+    /// columns taken by c on screen. Rough version of what nvim does, without its tables.
+    pub fn char_width(self, c: char) -> usize {
+        let c = c as u32;
+        match c {
+            // combining marks, zero width joiner and variation selectors stick to previous char.
+            0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF
+            | 0x200B..=0x200F | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F | 0xE0100..=0xE01EF => 0,
+            0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x20000..=0x3FFFD => 2,
+            0x1F300..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F900..=0x1F9FF
+                if self.emoji_wide => 2,
+            // east asian ambiguous, the common ones. Greek, cyrillic, arrows, math, box drawing,
+            // shapes and private use.
+            0x00A1 | 0x00A4 | 0x00A7..=0x00A8 | 0x00B0..=0x00B4 | 0x00B6..=0x00BA | 0x00BC..=0x00BF
+            | 0x00D7 | 0x00F7 | 0x0391..=0x03A9 | 0x03B1..=0x03C9 | 0x0401 | 0x0410..=0x044F | 0x0451
+            | 0x2010..=0x2027 | 0x2030..=0x203E | 0x2103 | 0x2116 | 0x2121..=0x2122 | 0x2160..=0x216B
+            | 0x2190..=0x21FF | 0x2200..=0x22FF | 0x2460..=0x24FF | 0x2500..=0x25FF | 0x2605..=0x2606
+            | 0x2640 | 0x2642 | 0xE000..=0xF8FF
+                if self.ambiwidth_double => 2,
+            _ => 1,
+        }
+    }
+
+    /// columns taken by text on screen.
+    pub fn text_width(self, text: &str) -> usize {
+        text.chars().map(|c| self.char_width(c)).sum()
+    }
+
+    /// cells for text, a wide char is followed by its continuation cell.
+    /// Zero width chars are kept in the cell of char before them.
+    pub fn text_cells(self, text: &str, hl: u16) -> Vec<Cell> {
+        let mut cells = Vec::with_capacity(text.len());
+        let mut grapheme = String::new();
+        let mut width = 0;
+        let push = |grapheme: &str, width: usize, cells: &mut Vec<Cell>| {
+            if grapheme.is_empty() { return }
+            let mut cell = Cell::new(grapheme, hl);
+            if width == 2 {
+                cell.width = 2;
+                cells.push(cell);
+                cells.push(Cell::new("", hl));
+            } else {
+                cells.push(cell);
+            }
+        };
+        for c in text.chars() {
+            let w = self.char_width(c);
+            if w == 0 && !grapheme.is_empty() {
+                grapheme.push(c);
+                continue;
+            }
+            push(&grapheme, width, &mut cells);
+            grapheme.clear();
+            grapheme.push(c);
+            width = w.max(1);
+        }
+        push(&grapheme, width, &mut cells);
+        return cells;
+    }
+
+    /// Write text in row of cells from col, not going beyond end. Returns col after the text.
+    /// A wide char which would not fit is replaced with space.
+    pub fn put_text(self, cells: &mut grid::Grid<Cell>, row: usize, col: usize, end: usize, text: &str, hl: u16) -> usize {
+        let end = end.min(cells.cols());
+        let mut col = col;
+        let text = self.text_cells(text, hl);
+        let mut text = text.into_iter().peekable();
+        while col < end && let Some(cell) = text.next() {
+            if cell.width == 2 {
+                if col + 1 >= end {
+                    cells[(row, col)] = Cell::new(" ", hl);
+                    return col + 1;
+                }
+                cells[(row, col)] = cell;
+                cells[(row, col + 1)] = text.next().unwrap();
+                col += 2;
+                continue;
+            }
+            cells[(row, col)] = cell;
+            col += 1;
+        }
+        return col;
+    }
+
+    /// cells of chunks, broken into lines at newlines and at width.
+    pub fn chunk_lines(self, chunks: &[(u16, String)], width: usize, base_hl: u16) -> Vec<Vec<Cell>> {
+        let mut lines = Vec::new();
+        let mut cells = Vec::new();
+        let end_line = |cells: Vec<Cell>, lines: &mut Vec<Vec<Cell>>| {
+            let len = cells.len();
+            let mut rows = wrap(cells, width, len, base_hl).0;
+            // the one for cursor after end of a full row.
+            if rows.len() > 1 && rows.last().unwrap().is_empty() { rows.pop(); }
+            lines.extend(rows);
+        };
+        for (hl, text) in chunks {
+            let hl = if *hl == 0 { base_hl } else { *hl };
+            let mut parts = text.split('\n');
+            cells.extend(self.text_cells(parts.next().unwrap_or(""), hl));
+            for part in parts {
+                end_line(core::mem::take(&mut cells), &mut lines);
+                cells.extend(self.text_cells(part, hl));
+            }
+        }
+        end_line(cells, &mut lines);
+        return lines;
+    }
+}
+
+/// fill cells of row from col to end with spaces.
+pub fn fill(cells: &mut grid::Grid<Cell>, row: usize, col: usize, end: usize, hl: u16) {
+    for col in col..end.min(cells.cols()) {
+        cells[(row, col)] = Cell::new(" ", hl);
+    }
+}
+
+/// break cells into rows of width. A wide char which would be cut, goes to next row.
+/// Returns rows, and row and col of cell at index cursor.
+pub fn wrap(cells: Vec<Cell>, width: usize, cursor: usize, base_hl: u16) -> (Vec<Vec<Cell>>, (usize, usize)) {
+    let mut rows = vec![Vec::with_capacity(width)];
+    let mut cursor_at = None;
+    for (i, cell) in cells.into_iter().enumerate() {
+        let row = rows.last_mut().unwrap();
+        let needed = if cell.width == 2 { 2 } else { 1 };
+        if cell.width != 0 && row.len() + needed > width {
+            row.resize(width, Cell::new(" ", base_hl));
+            rows.push(Vec::with_capacity(width));
+        }
+        if i == cursor { cursor_at = Some((rows.len() - 1, rows.last().unwrap().len())); }
+        rows.last_mut().unwrap().push(cell);
+    }
+    let cursor_at = cursor_at.unwrap_or_else(|| {
+        // after the end.
+        if rows.last().unwrap().len() >= width { rows.push(Vec::new()); }
+        (rows.len() - 1, rows.last().unwrap().len())
+    });
+    return (rows, cursor_at);
+}
@@ -0,0 +1,183 @@
+// What part of its buffer a window shows, from win_viewport. And animating the scroll of a window
+// over a few frames, with scroll region of terminal.
+use std::time::{Duration, Instant};
+use nvimapi::uievent;
+use suffixes::CastIt as _;
+use crate::terminal::Terminal;
+use super::{Data, data::{GridId, Position}};
+
+// a scroll is shown in this many steps at most.
+const MAX_FRAMES: usize = 8;
+const FRAME: Duration = Duration::from_millis(12);
+
+// all of it is kept, though not everything is used yet.
+#[allow(dead_code)]
+#[derive(Debug, Default, Clone)]
+pub struct Viewport {
+    // buffer lines, 0 based. botline is one past the last line shown.
+    pub topline: i64,
+    pub botline: i64,
+    pub curline: i64,
+    pub curcol: i64,
+    pub line_count: i64,
+    // lines scrolled since last win_viewport.
+    pub scroll_delta: i64,
+}
+
+/// a grid_scroll being shown in steps. Terminal rows of the region are scrolled by shown rows so
+/// far. Rows which are yet to come in, keep what terminal already has.
+#[derive(Debug)]
+pub struct ScrollAnim {
+    pub grid: GridId,
+    // of the grid, when it started. If the window moves, it's given up.
+    pub origin: Position,
+    // region on surface.
+    pub top: usize,
+    pub bot: usize,
+    pub left: usize,
+    pub right: usize,
+    // column of scrollbar, it doesn't scroll.
+    pub scrollbar_col: Option<usize>,
+    // like grid_scroll, positive when content moves up.
+    pub rows: i64,
+    // rows scrolled on terminal.
+    pub shown: usize,
+    // rows to be scrolled by next render.
+    pub target: usize,
+    pub next_frame: Instant,
+    // win_viewport said it's a scroll of the window, not lines inserted or deleted.
+    pub confirmed: bool,
+}
+
+impl Viewport {
+    pub fn new(event: &uievent::WinViewport) -> Self {
+        Self {
+            topline: event.topline,
+            botline: event.botline,
+            curline: event.curline,
+            curcol: event.curcol,
+            line_count: event.line_count,
+            scroll_delta: event.scroll_delta,
+        }
+    }
+}
+
+impl Viewport {
+    /// rows of thumb of a scrollbar height rows tall. None when whole buffer is shown.
+    pub fn thumb(&self, height: usize) -> Option<(usize, usize)> {
+        let total = self.line_count.max(1).u();
+        let shown = (self.botline - self.topline).max(0).u();
+        if shown >= total || height == 0 { return None }
+        let thumb_h = (height * shown).div_ceil(total).clamp(1, height);
+        let thumb_top = ((height * self.topline.max(0).u()) / total).min(height - thumb_h);
+        return Some((thumb_top, thumb_h));
+    }
+}
+
+impl ScrollAnim {
+    fn total(&self) -> usize {
+        self.rows.unsigned_abs().u()
+    }
+    fn done(&self) -> bool {
+        self.shown == self.total()
+    }
+    /// row of surface whose content is shown at row of terminal, with the scroll done so far.
+    /// None if terminal keeps what it has there.
+    pub fn src_row(&self, row: usize, col: usize) -> Option<usize> {
+        if row < self.top || row >= self.bot || col < self.left || col >= self.right { return Some(row) }
+        if self.scrollbar_col == Some(col) { return Some(row) }
+        let behind = self.total() - self.shown;
+        if self.rows > 0 {
+            row.checked_sub(behind).filter(|&src| src >= self.top)
+        } else {
+            Some(row + behind).filter(|&src| src < self.bot)
+        }
+    }
+}
+
+impl Data {
+    /// Start showing scroll in steps, if it can be done with scroll region of terminal. Returns false
+    /// if it can't, then it has to be scrolled at once.
+    pub fn start_scroll_anim(&mut self, term: &Terminal, scroll: &uievent::GridScroll) -> bool {
+        // a previous one is given up, render will print whatever is left.
+        self.scroll_anim = None;
+        let Some(grid) = self.grids.get(&scroll.grid) else {return false};
+        if !grid.shown || self.layout_dirty || self.front.size() != self.surface.size() { return false }
+        // one row would not look any different.
+        if scroll.rows.abs() < 2 || scroll.cols != 0 { return false }
+        let (rows, cols) = self.surface.size();
+        let origin = self.origin(scroll.grid, grid);
+        let top = origin.row.u() + scroll.top.u();
+        let bot = origin.row.u() + scroll.bot.u();
+        let left = origin.col.u() + scroll.left.u();
+        let right = origin.col.u() + scroll.right.u();
+        if bot > rows || right > cols || top + scroll.rows.unsigned_abs().u() >= bot { return false }
+        if !(left == 0 && right == cols) && !term.caps.lr_margins { return false }
+        let scrollbar_col = self.config.scrollbar.then(|| origin.col.u() + grid.size.w.u().saturating_sub(1));
+        self.scroll_anim = Some(ScrollAnim {
+            grid: scroll.grid, origin, top, bot, left, right, scrollbar_col,
+            rows: scroll.rows,
+            shown: 0,
+            target: 0,
+            next_frame: Instant::now(),
+            confirmed: false,
+        });
+        return true;
+    }
+    /// win_viewport of the grid came, it's a scroll if viewport moved.
+    pub fn confirm_scroll_anim(&mut self, grid: GridId, viewport: &Viewport) {
+        let Some(anim) = &mut self.scroll_anim else {return};
+        if anim.grid != grid || anim.confirmed { return }
+        anim.confirmed = viewport.scroll_delta != 0;
+        anim.next_frame = Instant::now();
+    }
+    /// At flush, scroll which wasn't confirmed is done at once.
+    pub fn settle_scroll_anim(&mut self) {
+        let Some(anim) = &mut self.scroll_anim else {return};
+        if !anim.confirmed {
+            anim.target = anim.total();
+        }
+    }
+    /// Scroll terminal to target of the animation. Called by render, after compose and before it
+    /// prints.
+    pub fn advance_scroll_anim(&mut self, term: &Terminal) {
+        let Some(anim) = &self.scroll_anim else {return};
+        let moved = match self.grids.get(&anim.grid) {
+            Some(g) if g.shown => {
+                let origin = self.origin(anim.grid, g);
+                origin.row != anim.origin.row || origin.col != anim.origin.col
+            },
+            _ => true,
+        };
+        if moved || self.front.size() != self.surface.size() {
+            // region may have other things now.
+            self.scroll_anim = None;
+            return;
+        }
+        let step = anim.target - anim.shown;
+        if step == 0 { return }
+        let (top, bot, left, right) = (anim.top, anim.bot, anim.left, anim.right);
+        let rows = if anim.rows > 0 { step.i64() } else { -step.i64() };
+        self.scroll_region(term, top, bot, left, right, rows);
+        if let Some(anim) = &mut self.scroll_anim { anim.shown = anim.target; }
+    }
+    /// called after render. Drops the animation once terminal shows it all.
+    pub fn end_scroll_anim(&mut self) {
+        if self.scroll_anim.as_ref().is_some_and(ScrollAnim::done) {
+            self.scroll_anim = None;
+        }
+    }
+    /// next frame of the animation, if it's time. True if something is to be rendered.
+    pub fn step_scroll_anim(&mut self, now: Instant) -> bool {
+        let Some(anim) = &mut self.scroll_anim else {return false};
+        if !anim.confirmed || anim.target == anim.total() || now < anim.next_frame { return false }
+        let step = anim.total().div_ceil(MAX_FRAMES);
+        anim.target = (anim.target + step).min(anim.total());
+        anim.next_frame = now + FRAME;
+        return true;
+    }
+    pub fn scroll_anim_deadline(&self) -> Option<Instant> {
+        let anim = self.scroll_anim.as_ref()?;
+        (anim.confirmed && anim.target < anim.total()).then_some(anim.next_frame)
+    }
+}
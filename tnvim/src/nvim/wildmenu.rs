@@ -0,0 +1,108 @@
+use rmpv::Value;
+use suffixes::CastIt as _;
+use crate::config::WildmenuLayout;
+use super::{Data, data::{Cell, Position, CMDLINE_GRID, CMDLINE_ZINDEX, WILDMENU_GRID}, text::Widths};
+
+#[derive(Debug, Default)]
+pub struct Wildmenu {
+    pub items: Vec<String>,
+    // -1 when nothing is selected.
+    pub selected: i64,
+    pub layout: WildmenuLayout,
+    // first item shown, when they don't fit.
+    pub first: usize,
+}
+
+impl Wildmenu {
+    pub fn new(items: Vec<Value>, layout: WildmenuLayout) -> Self {
+        let items = items.into_iter().map(|item| match item {
+            Value::String(s) => s.into_str().unwrap_or_default(),
+            _ => String::new(),
+        }).collect();
+        Self { items, selected: -1, layout, first: 0 }
+    }
+    fn selected(&self) -> Option<usize> {
+        (self.selected >= 0).then(|| self.selected.u())
+    }
+}
+
+impl Data {
+    /// Draw wildmenu just above the cmdline, ours if it's shown, else nvim's last row.
+    pub fn draw_wildmenu(&mut self) {
+        let (rows, cols) = self.surface.size();
+        let Some(wildmenu) = &self.wildmenu else {
+            self.hide_overlay(WILDMENU_GRID);
+            return;
+        };
+        if wildmenu.items.is_empty() || rows < 2 || cols == 0 {
+            self.hide_overlay(WILDMENU_GRID);
+            return;
+        }
+        let bottom = self.grids.get(&CMDLINE_GRID)
+            .filter(|g| g.shown)
+            .map(|g| g.pos.row.u())
+            .unwrap_or(rows - 1);
+        let hl = self.hl_group("StatusLine").unwrap_or(0);
+        let sel_hl = self.hl_group("WildMenu").unwrap_or(hl);
+        let (first, lines, width) = match wildmenu.layout {
+            WildmenuLayout::Horizontal => {
+                let (first, line) = horizontal(wildmenu, self.widths, cols, hl, sel_hl);
+                (first, vec![line], cols)
+            },
+            WildmenuLayout::Vertical => vertical(wildmenu, self.widths, bottom.min(rows / 2).max(1), cols, hl, sel_hl),
+        };
+        if let Some(wildmenu) = &mut self.wildmenu { wildmenu.first = first; }
+        let pos = Position::new(0, bottom.saturating_sub(lines.len()).u16());
+        self.set_overlay(WILDMENU_GRID, pos, width, lines, hl, CMDLINE_ZINDEX + 1);
+    }
+}
+
+// items separated by two spaces. < and > show there are more items on that side.
+fn horizontal(wildmenu: &Wildmenu, widths: Widths, cols: usize, hl: u16, sel_hl: u16) -> (usize, Vec<Cell>) {
+    let item_widths: Vec<usize> = wildmenu.items.iter().map(|item| widths.text_width(item) + 2).collect();
+    // room for the markers.
+    let room = cols.saturating_sub(4).max(1);
+    let fits = |first: usize, last: usize| item_widths[first..=last].iter().sum::<usize>() <= room;
+    let mut first = wildmenu.first.min(item_widths.len() - 1);
+    if let Some(selected) = wildmenu.selected() {
+        let selected = selected.min(item_widths.len() - 1);
+        if selected < first { first = selected; }
+        while first < selected && !fits(first, selected) { first += 1; }
+    }
+    let mut line = widths.text_cells(if first > 0 { "< " } else { "  " }, hl);
+    let mut used = 0;
+    let mut last = first;
+    for (i, item) in wildmenu.items.iter().enumerate().skip(first) {
+        if used + item_widths[i] > room && i > first { break }
+        let item_hl = if wildmenu.selected() == Some(i) { sel_hl } else { hl };
+        line.extend(widths.text_cells(item, item_hl));
+        line.extend(widths.text_cells("  ", hl));
+        used += item_widths[i];
+        last = i;
+    }
+    if last + 1 < wildmenu.items.len() {
+        line.truncate(cols.saturating_sub(2));
+        line.resize(cols.saturating_sub(2), Cell::new(" ", hl));
+        line.extend(widths.text_cells(" >", hl));
+    }
+    return (first, line);
+}
+
+// one item per row, scrolled to keep selected in view.
+fn vertical(wildmenu: &Wildmenu, widths: Widths, max_height: usize, cols: usize, hl: u16, sel_hl: u16) -> (usize, Vec<Vec<Cell>>, usize) {
+    let height = wildmenu.items.len().min(max_height);
+    let mut first = wildmenu.first.min(wildmenu.items.len() - height);
+    if let Some(selected) = wildmenu.selected() {
+        if selected < first { first = selected; }
+        if selected >= first + height { first = selected + 1 - height; }
+    }
+    let width = wildmenu.items.iter().map(|item| widths.text_width(item) + 2).max().unwrap_or(0).min(cols);
+    let lines = (first..first + height).map(|i| {
+        let item_hl = if wildmenu.selected() == Some(i) { sel_hl } else { hl };
+        let mut line = vec![Cell::new(" ", item_hl)];
+        line.extend(widths.text_cells(&wildmenu.items[i], item_hl));
+        line.resize(width, Cell::new(" ", item_hl));
+        line
+    }).collect();
+    return (first, lines, width);
+}
@@ -1,11 +1,12 @@
 use std::rc::Rc;
+use suffixes::CastIt as _;
 use crate::terminal;
 use terminal::event::{KeyCode, KeyModifiers};
 #[allow(unused_imports)]
 use log::{debug, trace};
-use nvimapi::{Nvimapi, NvimapiNr};
+use nvimapi::{Nvimapi, NvimapiNr, uievent::Tabpage};
 use tokio::sync::mpsc::{self};
-use crate::{TERM_INPUT_BUFFER_SIZE, app::App};
+use crate::{TERM_INPUT_BUFFER_SIZE, app::App, nvim::{check, gone, tabline}};
 
 pub async fn input_from_term(this: Rc<App>, nvim: impl Nvimapi) {
     let (tx, mut rx) = mpsc::channel::<terminal::event::Event>(TERM_INPUT_BUFFER_SIZE);
@@ -16,7 +17,37 @@ pub async fn input_from_term(this: Rc<App>, nvim: impl Nvimapi) {
     ;
 }
 
-async fn on_mouse(_app: &App, nvim: &impl Nvimapi, mouse_event: terminal::event::MouseEvent) {
+async fn on_mouse(app: &App, nvim: &impl Nvimapi, mouse_event: terminal::event::MouseEvent) {
+    use crossterm::event::{MouseButton, MouseEventKind};
+    let top_rows = app.nvimdata.borrow().top_rows;
+    if mouse_event.row < top_rows {
+        // tabline
+        let target = app.nvimdata.borrow().tabline_target(mouse_event.column);
+        if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind && let Some(target) = target {
+            match target {
+                tabline::Target::Tab(tab) => check(nvim.nr().set_current_tabpage(&tab), "set_current_tabpage"),
+                tabline::Target::Buffer(buffer) => check(nvim.nr().set_current_buf(&buffer), "set_current_buf"),
+            }
+        }
+        return;
+    }
+    let pum_item = app.nvimdata.borrow().popupmenu_item_at(mouse_event.column, mouse_event.row);
+    if let MouseEventKind::Down(MouseButton::Left) = mouse_event.kind
+        && let Some(item) = pum_item
+    {
+        // popupmenu is drawn by us, nvim doesn't know what's there.
+        check(nvim.nr().select_popupmenu_item(item.i64(), true, true, nvimapi::Pairs::<rmpv::Value>::new()), "select_popupmenu_item");
+        return;
+    }
+    let owner = app.nvimdata.borrow().owner_at(mouse_event.column, mouse_event.row);
+    if let MouseEventKind::ScrollUp | MouseEventKind::ScrollDown = mouse_event.kind
+        && owner == Some(crate::nvim::data::MSG_HISTORY_GRID)
+    {
+        let lines = if mouse_event.kind == MouseEventKind::ScrollUp { 3 } else { -3 };
+        app.nvimdata.borrow_mut().scroll_history(lines);
+        app.redraw();
+        return;
+    }
     let (btn, action) = 
         match mouse_event.kind {
             crossterm::event::MouseEventKind::Down(mouse_button) => {
@@ -63,7 +94,8 @@ async fn on_mouse(_app: &App, nvim: &impl Nvimapi, mouse_event: terminal::event:
     let mut mods = Vec::new();
     modifier_map(mouse_event.modifiers, &mut mods);
     let mods: String = mods.iter().collect();
-    nvim.nr().input_mouse(btn, action, &mods, 1, mouse_event.row.into(), mouse_event.column.into()).unwrap();
+    let row = mouse_event.row - top_rows;
+    check(nvim.nr().input_mouse(btn, action, &mods, 1, row.into(), mouse_event.column.into()), "input_mouse");
     // nvim.input_mouse(btn, action, &mods, 1, mouse_event.row.into(), mouse_event.column.into()).await.unwrap();
     fn btn_str(btn: crossterm::event::MouseButton) -> &'static str {
         match btn {
@@ -76,56 +108,96 @@ async fn on_mouse(_app: &App, nvim: &impl Nvimapi, mouse_event: terminal::event:
 
 async fn on_paste(_: &App, nvim: &impl Nvimapi, paste: String) {
     log::trace!("paste: {paste}");
-    nvim.nr().paste(&paste, true, -1).unwrap();
+    check(nvim.nr().paste(&paste, true, -1), "paste");
 }
 
 async fn on_resize(app: &App, nvim: &impl Nvimapi, w: u16, h: u16) {
     let attached = app.nvimdata.borrow().attached;
+    app.nvimdata.borrow_mut().ui_size = crate::nvim::data::Size { w, h };
+    let (w, h) = app.nvimdata.borrow().nvim_request_size();
     if attached {
-        nvim.nr().ui_try_resize(w.into(), h.into()).unwrap();
+        check(nvim.nr().ui_try_resize(w.into(), h.into()), "ui_try_resize");
     }
     else {
-        let current_tab = nvim.get_current_tabpage().await.unwrap();
+        let Some(current_tab) = current_tabpage(nvim).await else {return};
         if let Some(my_tab) = &app.nvimdata.borrow().my_tab {
-            nvim.nr().set_current_tabpage(my_tab).unwrap();
+            check(nvim.nr().set_current_tabpage(my_tab), "set_current_tabpage");
         }
         crate::attach(nvim, w, h);
-        nvim.nr().ui_detach().unwrap();
-        nvim.nr().set_current_tabpage(&current_tab).unwrap();
+        check(nvim.nr().ui_detach(), "ui_detach");
+        check(nvim.nr().set_current_tabpage(&current_tab), "set_current_tabpage");
     }
-    app.nvimdata.borrow_mut().ui_size = crate::nvim::data::Size { w, h };
 }
 
-async fn on_key(_: &App, nvim: &impl Nvimapi, key_event: terminal::event::KeyEvent) {
+async fn on_key(app: &App, nvim: &impl Nvimapi, key_event: terminal::event::KeyEvent) {
     trace!("on key: {key_event:?}");
+    let history_open = app.nvimdata.borrow().messages.history.is_some();
+    if history_open && on_history_key(app, key_event) {
+        return;
+    }
     if let Some(to_send) = to_nvim_input_key(key_event) {
-        nvim.nr().input(&to_send).unwrap();
+        check(nvim.nr().input(&to_send), "input");
         trace!("sent: {to_send}");
     }
 }
 
 async fn on_focus_lost(app: &App, nvim: &impl Nvimapi) {
+    app.nvimdata.borrow_mut().unfocused = true;
     // nvim.nr().ui_set_focus(false).unwrap();
-    nvim.nr().ui_detach().unwrap();
+    check(nvim.nr().ui_detach(), "ui_detach");
     app.nvimdata.borrow_mut().attached = false;
-    let current_tab = nvim.get_current_tabpage().await.unwrap();
+    let Some(current_tab) = current_tabpage(nvim).await else {return};
+    tabline::broadcast_tab(nvim, Some(&current_tab));
     app.nvimdata.borrow_mut().my_tab = Some(current_tab);
 }
+// None if nvim couldn't tell, it's logged.
+async fn current_tabpage(nvim: &impl Nvimapi) -> Option<Tabpage> {
+    match nvim.get_current_tabpage().await {
+        Ok(tab) => Some(tab),
+        // nvim is gone, disconnected takes care of it.
+        Err(e) if e.disconnected() => None,
+        Err(e) => {
+            log::warn!("get_current_tabpage: {e}");
+            None
+        },
+    }
+}
 async fn on_focus_gained(app: &App, nvim: &impl Nvimapi) {
+    app.nvimdata.borrow_mut().unfocused = false;
     // nvim.nr().ui_set_focus(true).unwrap();
     let my_tab = app.nvimdata.borrow().my_tab.clone();
     if let Some(my_tab) = my_tab {
         // some delay, so that the other ui which lost focus, can save it's current tab before I
         // change it.
         tokio::time::sleep(core::time::Duration::from_millis(10)).await;
-        nvim.nr().set_current_tabpage(&my_tab).unwrap();
+        check(nvim.nr().set_current_tabpage(&my_tab), "set_current_tabpage");
     }
-    let size = app.nvimdata.borrow().ui_size.clone();
-    crate::attach(nvim, size.w, size.h);
+    let (w, h) = app.nvimdata.borrow().nvim_request_size();
+    crate::attach(nvim, w, h);
     app.nvimdata.borrow_mut().attached = true;
     // debug!("focus_gained");
 }
 
+// :messages is open. Returns true if the key was for it.
+fn on_history_key(app: &App, key_event: terminal::event::KeyEvent) -> bool {
+    if key_event.kind == terminal::event::KeyEventKind::Release { return false }
+    let (rows, _) = app.nvimdata.borrow().surface.size();
+    let page = (rows / 2).max(1).isize();
+    let mut data = app.nvimdata.borrow_mut();
+    let consumed = match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => { data.scroll_history(-1); true },
+        KeyCode::Char('k') | KeyCode::Up => { data.scroll_history(1); true },
+        KeyCode::PageDown => { data.scroll_history(-page); true },
+        KeyCode::PageUp => { data.scroll_history(page); true },
+        KeyCode::Char('q') | KeyCode::Esc => { data.close_history(); true },
+        // anything else closes it, and goes to nvim.
+        _ => { data.close_history(); false },
+    };
+    drop(data);
+    app.redraw();
+    return consumed;
+}
+
 fn to_nvim_input_key(key_event: terminal::event::KeyEvent) -> Option<String> {
     use terminal::event::{KeyCode};
     let mut rv = String::new();
@@ -219,6 +291,11 @@ fn crosscode_event_loop(tx: mpsc::Sender<terminal::event::Event>) {
 
 async fn handle_event(this: &App, nvim: &impl Nvimapi, event: terminal::event::Event) {
     use terminal::event::Event;
+    // nothing goes to nvim anymore.
+    if this.nvimdata.borrow().server_gone {
+        gone::on_event(event);
+        return;
+    }
     match event {
         Event::FocusGained => on_focus_gained(this, nvim).await,
         Event::FocusLost => on_focus_lost(this, nvim).await,
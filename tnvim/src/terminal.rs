@@ -1,11 +1,15 @@
 use crate::error;
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use std::io::{Write, stdout};
 use crossterm::{ExecutableCommand, QueueableCommand, style::Print, terminal::{self, LeaveAlternateScreen}};
 pub use crossterm::event;
+pub use crossterm::style::{Attribute, Attributes};
 pub struct Terminal {
     // out: RefCell<std::io::Stdout>,
     out: RefCell<Vec<u8>>,
+    pub caps: Capabilities,
+    // attributes or underline color were set by last set_style. They need a reset before next one.
+    styled: Cell<bool>,
 }
 type Ret<'t> = error::Result<&'t Terminal>;
 impl Terminal {
@@ -31,6 +35,10 @@ impl Terminal {
         stdout().execute(crossterm::event::EnableMouseCapture)?;
         Ok(self )
     }
+    pub fn disable_mouse_events(&self) -> Ret<'_> {
+        stdout().execute(crossterm::event::DisableMouseCapture)?;
+        Ok(self )
+    }
     pub fn enable_focus_events(&self) -> Ret<'_> {
         stdout().execute(crossterm::event::EnableFocusChange)?;
         Ok(self )
@@ -41,11 +49,38 @@ impl Terminal {
         Ok(self)
     }
 
+    /// Tell terminal the current directory with OSC 7, new tabs and splits of it open there.
+    pub fn set_cwd(&self, path: &str) -> error::Result<&Self> {
+        let host = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+        let mut url = format!("file://{}", host.trim());
+        for byte in path.bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                url.push(byte as char);
+            } else {
+                url.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        write!(self.out.borrow_mut(), "\x1b]7;{url}\x1b\\")?;
+        Ok(self)
+    }
+
     pub fn move_cursor(&self, col: u16, row: u16) -> error::Result<&Self> {
         self.out.borrow_mut().queue(crossterm::cursor::MoveTo(col, row))?;
         Ok(self)
     }
 
+    pub fn bell(&self) -> Ret<'_> {
+        self.out.borrow_mut().queue(Print("\x07"))?;
+        Ok(self)
+    }
+    pub fn hide_cursor(&self) -> Ret<'_> {
+        self.out.borrow_mut().queue(crossterm::cursor::Hide)?;
+        Ok(self)
+    }
+    pub fn show_cursor(&self) -> Ret<'_> {
+        self.out.borrow_mut().queue(crossterm::cursor::Show)?;
+        Ok(self)
+    }
     pub fn print(&self, text: &str) -> Ret<'_> {
         self.out.borrow_mut().queue(Print(text))?;
         Ok(self)
@@ -54,17 +89,118 @@ impl Terminal {
         let (w,h) = crossterm::terminal::size()?;
         Ok((w, h))
     }
+    /// DECSTBM. bot is exclusive.
+    pub fn set_scroll_region(&self, top: u16, bot: u16) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b[{};{}r", top + 1, bot)?;
+        Ok(self)
+    }
+    pub fn reset_scroll_region(&self) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b[r")?;
+        Ok(self)
+    }
+    /// DECLRMM + DECSLRM. right is exclusive. Check caps.lr_margins before using.
+    pub fn set_lr_margins(&self, left: u16, right: u16) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b[?69h\x1b[{};{}s", left + 1, right)?;
+        Ok(self)
+    }
+    pub fn reset_lr_margins(&self) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b[?69l")?;
+        Ok(self)
+    }
+    /// content of scroll region moves up.
+    pub fn scroll_up(&self, rows: u16) -> Ret<'_> {
+        self.out.borrow_mut().queue(crossterm::terminal::ScrollUp(rows))?;
+        Ok(self)
+    }
+    /// content of scroll region moves down.
+    pub fn scroll_down(&self, rows: u16) -> Ret<'_> {
+        self.out.borrow_mut().queue(crossterm::terminal::ScrollDown(rows))?;
+        Ok(self)
+    }
+    /// OSC 8. Printed text is a link to url, until close_link.
+    pub fn open_link(&self, url: &str) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b]8;;{url}\x1b\\")?;
+        Ok(self)
+    }
+    pub fn close_link(&self) -> Ret<'_> {
+        write!(self.out.borrow_mut(), "\x1b]8;;\x1b\\")?;
+        Ok(self)
+    }
     pub fn clear_screen(&self) -> Ret<'_> {
         self.out.borrow_mut().queue(crossterm::terminal::Clear(terminal::ClearType::All))?;
         Ok(self)
     }
     pub(crate) fn set_colors(&self, bg: nvimapi::Color, fg:nvimapi::Color) -> Ret<'_> {
+        if self.caps.colors == ColorDepth::Ansi16 {
+            // crossterm sends even these as 38;5;n, which 16 color terminals don't understand.
+            let fg = ansi16(fg);
+            let bg = ansi16(bg);
+            let fg = if fg < 8 { 30 + fg } else { 90 + fg - 8 };
+            let bg = if bg < 8 { 40 + bg } else { 100 + bg - 8 };
+            write!(self.out.borrow_mut(), "\x1b[{fg};{bg}m")?;
+            return Ok(self);
+        }
         self.out.borrow_mut().queue(crossterm::style::SetColors(crossterm::style::Colors {
-            foreground: Some(crossterm::style::Color::Rgb { r: fg.r, g: fg.g, b: fg.b }),
-            background: Some(crossterm::style::Color::Rgb { r: bg.r, g: bg.g, b: bg.b }),
+            foreground: Some(self.term_color(fg)),
+            background: Some(self.term_color(bg)),
         }))?;
         Ok(self)
     }
+    /// nearest color the terminal can show.
+    fn term_color(&self, color: nvimapi::Color) -> crossterm::style::Color {
+        use crossterm::style::Color;
+        match self.caps.colors {
+            ColorDepth::TrueColor => Color::Rgb { r: color.r, g: color.g, b: color.b },
+            ColorDepth::Ansi256 => Color::AnsiValue(ansi256(color)),
+            ColorDepth::Ansi16 => Color::AnsiValue(ansi16(color)),
+        }
+    }
+    /// set colors, and replace the attributes of previous style.
+    pub(crate) fn set_style(
+        &self, bg: nvimapi::Color, fg: nvimapi::Color,
+        underline_color: Option<nvimapi::Color>, attributes: Attributes,
+    ) -> Ret<'_> {
+        use crossterm::style::{SetAttribute, SetAttributes, SetUnderlineColor};
+        if self.styled.get() {
+            // reset clears colors too, so it has to come first.
+            self.out.borrow_mut().queue(SetAttribute(Attribute::Reset))?;
+        }
+        self.set_colors(bg, fg)?;
+        let mut out = self.out.borrow_mut();
+        if !attributes.is_empty() {
+            out.queue(SetAttributes(attributes))?;
+        }
+        // 16 color terminals won't know about underline color either.
+        if let Some(sp) = underline_color && self.caps.colors != ColorDepth::Ansi16 {
+            out.queue(SetUnderlineColor(self.term_color(sp)))?;
+        }
+        self.styled.set(!attributes.is_empty() || underline_color.is_some());
+        Ok(self)
+    }
+    /// like set_style, with colors of terminal's palette. None is terminal's default color.
+    pub(crate) fn set_cterm_style(&self, bg: Option<u8>, fg: Option<u8>, attributes: Attributes) -> Ret<'_> {
+        use crossterm::style::{Color, SetAttribute, SetAttributes};
+        if self.styled.get() {
+            self.out.borrow_mut().queue(SetAttribute(Attribute::Reset))?;
+        }
+        let mut out = self.out.borrow_mut();
+        if self.caps.colors == ColorDepth::Ansi16 {
+            // same as set_colors, 38;5;n is not understood. 39 and 49 are the defaults.
+            let fg = fg.filter(|&c| c < 16).map_or(39, |c| if c < 8 { 30 + c } else { 90 + c - 8 });
+            let bg = bg.filter(|&c| c < 16).map_or(49, |c| if c < 8 { 40 + c } else { 100 + c - 8 });
+            write!(out, "\x1b[{fg};{bg}m")?;
+        } else {
+            out.queue(crossterm::style::SetColors(crossterm::style::Colors {
+                foreground: Some(fg.map_or(Color::Reset, Color::AnsiValue)),
+                background: Some(bg.map_or(Color::Reset, Color::AnsiValue)),
+            }))?;
+        }
+        if !attributes.is_empty() {
+            out.queue(SetAttributes(attributes))?;
+        }
+        self.styled.set(!attributes.is_empty());
+        Ok(self)
+    }
     pub(crate) fn set_cursor_shape(&self, cursor_shape: CursorShape) -> Ret<'_> {
         use crossterm::cursor::SetCursorStyle;
         let cursor_command = 
@@ -90,9 +226,20 @@ impl Terminal {
         return Ok(self);
     }
 }
+#[cfg(test)]
+impl Terminal {
+    /// what would have been written on flush.
+    pub fn take_output(&self) -> Vec<u8> {
+        core::mem::take(&mut self.out.borrow_mut())
+    }
+}
 pub fn leave_alternate_screen() {
     stdout().execute(LeaveAlternateScreen).unwrap();
 }
+/// in case it was hidden while nvim was busy.
+pub fn restore_cursor() {
+    let _ = stdout().execute(crossterm::cursor::Show);
+}
 pub fn disable_raw_mode() {
     crossterm::terminal::disable_raw_mode().unwrap();
 }
@@ -123,7 +270,153 @@ pub enum CursorShape {
 }
 impl Default for Terminal {
     fn default() -> Self {
-        Self { out: RefCell::new(Vec::with_capacity(50 * 100)), }
+        Self {
+            out: RefCell::new(Vec::with_capacity(50 * 100)),
+            caps: Capabilities::default(),
+            styled: Cell::new(false),
+        }
         // Self { out: RefCell::new(stdout()), }
     }
 }
+
+/// what the terminal we are running in can do, beyond what every terminal does.
+#[derive(Debug, Default, Clone)]
+pub struct Capabilities {
+    /// left and right margins for scroll region (DECSLRM).
+    pub lr_margins: bool,
+    pub colors: ColorDepth,
+}
+
+/// how many colors the terminal can show.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    #[default]
+    TrueColor,
+    /// xterm 256 color palette.
+    Ansi256,
+    Ansi16,
+}
+impl ColorDepth {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "truecolor" | "24bit" => Some(Self::TrueColor),
+            "256" => Some(Self::Ansi256),
+            "16" => Some(Self::Ansi16),
+            _ => None,
+        }
+    }
+    /// from COLORTERM, TERM and terminfo.
+    fn detect(term: &str) -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if matches!(colorterm.as_str(), "truecolor" | "24bit") || term.ends_with("-direct") {
+            return Self::TrueColor;
+        }
+        if term.contains("256color") {
+            return Self::Ansi256;
+        }
+        let tput = std::process::Command::new("tput")
+            .arg("colors")
+            .stderr(std::process::Stdio::null())
+            .output();
+        let colors = tput.ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| String::from_utf8(out.stdout).ok())
+            .and_then(|colors| colors.trim().parse::<u32>().ok());
+        match colors {
+            Some(colors) if colors >= 1 << 24 => Self::TrueColor,
+            Some(colors) if colors >= 256 => Self::Ansi256,
+            Some(_) => Self::Ansi16,
+            // no terminfo. Most terminals around are xterm like.
+            None if term.starts_with("xterm") => Self::Ansi256,
+            None => Self::Ansi16,
+        }
+    }
+}
+
+/// levels of each component in the 6x6x6 cube of xterm palette, starting at 16.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+/// default palette of the first 16 colors, as xterm has them.
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    return d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2);
+}
+/// nearest color from 16..256 of xterm palette. First 16 are left out, terminals change them.
+fn ansi256(color: nvimapi::Color) -> u8 {
+    let rgb = (color.r, color.g, color.b);
+    let level = |c: u8| {
+        (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs()).unwrap()
+    };
+    let (r, g, b) = (level(color.r), level(color.g), level(color.b));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+    let cube_index = 16 + 36 * r + 6 * g + b;
+    // grays from 232 to 255 are 8, 18, .., 238.
+    let avg = (color.r as u32 + color.g as u32 + color.b as u32) / 3;
+    let gray_step = (avg.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = (gray_level, gray_level, gray_level);
+    if distance(rgb, gray) < distance(rgb, cube) {
+        return 232 + gray_step;
+    }
+    return cube_index as u8;
+}
+fn ansi16(color: nvimapi::Color) -> u8 {
+    let rgb = (color.r, color.g, color.b);
+    return (0..16u8).min_by_key(|&i| distance(rgb, ANSI16[i as usize])).unwrap();
+}
+impl Capabilities {
+    /// from the environment. Color depth is asked only when colors is not given.
+    pub fn detect(colors: Option<ColorDepth>) -> Self {
+        let env = |key: &str| std::env::var(key).unwrap_or_default();
+        // multiplexers don't support DECSLRM, even if the terminal outside does.
+        // And they pass on the env variables of outer terminal.
+        let term = env("TERM");
+        let multiplexed = std::env::var_os("TMUX").is_some()
+            || term.starts_with("screen")
+            || term.starts_with("tmux");
+        let lr_margins = !multiplexed && (
+            std::env::var_os("XTERM_VERSION").is_some()
+            || matches!(env("TERM_PROGRAM").as_str(), "WezTerm" | "iTerm.app")
+        );
+        let colors = colors.unwrap_or_else(|| ColorDepth::detect(&term));
+        Self { lr_margins, colors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nvimapi::Color;
+    use super::{ansi16, ansi256};
+
+    fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b }
+    }
+    #[test]
+    fn ansi256_cube() {
+        assert_eq!(ansi256(rgb(0, 0, 0)), 16);
+        assert_eq!(ansi256(rgb(255, 0, 0)), 196);
+        assert_eq!(ansi256(rgb(255, 255, 255)), 231);
+        assert_eq!(ansi256(rgb(0, 95, 135)), 24);
+        // off the cube levels, nearest is taken.
+        assert_eq!(ansi256(rgb(250, 10, 0)), 196);
+    }
+    #[test]
+    fn ansi256_grays() {
+        assert_eq!(ansi256(rgb(128, 128, 128)), 244);
+        assert_eq!(ansi256(rgb(8, 8, 8)), 232);
+        assert_eq!(ansi256(rgb(238, 238, 238)), 255);
+    }
+    #[test]
+    fn ansi16_nearest() {
+        assert_eq!(ansi16(rgb(0, 0, 0)), 0);
+        assert_eq!(ansi16(rgb(200, 0, 0)), 1);
+        assert_eq!(ansi16(rgb(250, 0, 0)), 9);
+        assert_eq!(ansi16(rgb(255, 255, 255)), 15);
+        assert_eq!(ansi16(rgb(100, 100, 250)), 12);
+    }
+}
@@ -0,0 +1,37 @@
+use std::{rc::Rc, time::Instant};
+use crate::{app::App, nvim::Data};
+
+/// Wakes up at the earliest deadline of things which change with time, like messages expiring,
+/// and redraws. Whatever sets a new deadline, calls app.ticker.notify_one().
+pub async fn run(app: Rc<App>) {
+    loop {
+        let deadline = app.nvimdata.borrow().next_deadline();
+        match deadline {
+            Some(deadline) => {
+                let deadline = tokio::time::Instant::from_std(deadline);
+                let _ = tokio::time::timeout_at(deadline, app.ticker.notified()).await;
+            },
+            None => app.ticker.notified().await,
+        }
+        let changed = app.nvimdata.borrow_mut().tick(std::time::Instant::now());
+        if changed {
+            app.redraw();
+        }
+    }
+}
+
+impl Data {
+    /// Advance whatever changes with time. true if something changed on screen.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let expired = self.expire_messages(now);
+        let scrolled = self.step_scroll_anim(now);
+        let spinner = self.step_busy(now);
+        let flashed = self.step_flash(now);
+        return expired || scrolled || spinner || flashed;
+    }
+    /// when tick should be called next.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        [self.messages.next_deadline(), self.scroll_anim_deadline(), self.busy_deadline(), self.flash_until]
+            .into_iter().flatten().min()
+    }
+}